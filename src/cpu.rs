@@ -32,4 +32,21 @@ impl Cpu {
             unreachable!();
         }
     }
+
+    /// Panics if the binary was built for the other CPU, i.e. [`Cpu::CPU`] doesn't match
+    /// [`from_device`](Self::from_device)
+    ///
+    /// Call this early in `main`, a binary built with `cm0p` running on the Cortex-M4 (or vice
+    /// versa) accesses the registers of the other CPU otherwise.
+    pub fn verify_feature_matches_hardware() {
+        let device = Self::from_device();
+
+        if device != Self::CPU {
+            panic!(
+                "binary built for {:?}, but running on {:?}, check the `cm4` / `cm0p` feature",
+                Self::CPU,
+                device
+            );
+        }
+    }
 }
@@ -0,0 +1,73 @@
+//! Cyclic Redundancy Check calculation unit (CRC)
+//!
+//! The unit is used with its reset configuration: CRC-32 polynomial `0x04C1_1DB7`, initial value
+//! `0xFFFF_FFFF`, no bit reversal of the input or output.
+
+use crate::pac::CRC;
+use crate::rcc::rec;
+
+pub trait CrcExt {
+    fn constrain(self, rec: &mut rec::CRC) -> Crc;
+}
+
+impl CrcExt for CRC {
+    fn constrain(self, rec: &mut rec::CRC) -> Crc {
+        Crc::new(self, rec)
+    }
+}
+
+pub struct Crc {
+    crc: CRC,
+}
+
+impl Crc {
+    pub fn new(crc: CRC, rec: &mut rec::CRC) -> Self {
+        rec.enable();
+        rec.reset();
+
+        Self { crc }
+    }
+
+    /// Restarts the calculation from the initial value
+    pub fn reset(&mut self) {
+        self.crc.cr.modify(|_, w| w.reset().set_bit());
+    }
+
+    /// Feeds a 32-bit word, most significant bit first
+    pub fn feed_word(&mut self, word: u32) {
+        self.crc.dr.write(|w| unsafe { w.bits(word) });
+    }
+
+    /// Feeds bytes one by one
+    pub fn feed_bytes(&mut self, bytes: &[u8]) {
+        let dr = self.crc.dr.as_ptr().cast::<u8>();
+
+        for &byte in bytes {
+            // SAFETY: DR supports byte accesses, which feed 8 bits into the calculation
+            unsafe { core::ptr::write_volatile(dr, byte) };
+        }
+    }
+
+    /// Feeds `bytes` in words, as they are laid out in memory
+    ///
+    /// The trailing bytes are fed one by one.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let chunks = bytes.chunks_exact(4);
+        let rest = chunks.remainder();
+
+        for chunk in chunks {
+            self.feed_word(u32::from_ne_bytes(chunk.try_into().unwrap()));
+        }
+
+        self.feed_bytes(rest);
+    }
+
+    /// Current checksum
+    pub fn result(&self) -> u32 {
+        self.crc.dr.read().bits()
+    }
+
+    pub fn free(self) -> CRC {
+        self.crc
+    }
+}
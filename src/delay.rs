@@ -1,8 +1,8 @@
 use core::convert::Infallible;
 use cortex_m::peripheral::syst::SystClkSource;
 use cortex_m::peripheral::SYST;
-use embedded_hal::delay::blocking::DelayUs;
-use embedded_hal_02::timer::CountDown;
+use embedded_hal::delay::DelayNs;
+use embedded_hal_02::timer::{CountDown, Periodic};
 
 use crate::rcc::{Clocks, TrustedClocks};
 use crate::time::Hertz;
@@ -16,7 +16,7 @@ pub trait DelayExt {
 }
 
 impl DelayExt for SYST {
-    fn delay<'a, CLOCKS>(self, clocks: impl Clocks) -> Delay<CLOCKS>
+    fn delay<'a, CLOCKS>(self, clocks: CLOCKS) -> Delay<CLOCKS>
     where
         CLOCKS: Clocks + TrustedClocks<'a>,
     {
@@ -30,14 +30,22 @@ pub struct Delay<CLOCKS> {
     syst: SYST,
 }
 
-/// Implements [CountDown](embedded_hal::timer::CountDown) for the System timer (SysTick).
+/// Implements [CountDown](embedded_hal_02::timer::CountDown) for the System timer (SysTick).
+///
+/// `embedded-hal` 1.0 has no timer traits, the same API is available as inherent methods.
 pub struct Countdown<'a, CLOCKS> {
     clocks: CLOCKS,
     syst: &'a mut SYST,
     total_rvr: u64,
+    period_rvr: u64,
     finished: bool,
 }
 
+/// [`Countdown`] restarting with the same period each time it finishes
+///
+/// Usable as a fixed-interval tick source, implements [`Periodic`].
+pub struct PeriodicCountdown<'a, CLOCKS>(Countdown<'a, CLOCKS>);
+
 impl<'a, CLOCKS> Countdown<'a, CLOCKS>
 where
     CLOCKS: Clocks + TrustedClocks<'a>,
@@ -48,10 +56,46 @@ where
             syst,
             clocks,
             total_rvr: 0,
+            period_rvr: 0,
             finished: true,
         }
     }
 
+    /// Starts a new count down of `count`
+    pub fn start(&mut self, count: fugit::MicrosDurationU32) {
+        let us = count.ticks();
+
+        self.period_rvr = systick_ticks(&self.clocks, u64::from(us), 1_000_000);
+        self.total_rvr = self.period_rvr;
+
+        self.start_wait();
+    }
+
+    /// Non-blockingly waits until the count down finishes
+    ///
+    /// Keeps returning `Ok` afterwards, until the count down is started again.
+    pub fn wait(&mut self) -> nb::Result<(), Infallible> {
+        if self.finished {
+            return Ok(());
+        }
+
+        if self.syst.has_wrapped() {
+            self.syst.disable_counter();
+            self.start_wait();
+
+            if self.finished {
+                return Ok(());
+            }
+        }
+
+        Err(nb::Error::WouldBlock)
+    }
+
+    fn reload(&mut self) {
+        self.total_rvr = self.period_rvr;
+        self.start_wait();
+    }
+
     /// start a wait cycle and sets finished to true if [CountdownUs] is done waiting.
     fn start_wait(&mut self) {
         // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
@@ -86,38 +130,57 @@ where
     where
         T: Into<Self::Time>,
     {
-        let us = count.into().ticks();
+        Countdown::start(self, count.into());
+    }
 
-        // With c_ck up to 480e6, we need u64 for delays > 8.9s
+    fn wait(&mut self) -> nb::Result<(), Infallible> {
+        Countdown::wait(self)
+    }
+}
 
-        self.total_rvr = if cfg!(not(feature = "revision_v")) {
-            // See errata ES0392 §2.2.3. Revision Y does not have the /8 divider
-            u64::from(us) * u64::from(self.clocks.c_ck().raw() / 1_000_000)
-        } else if cfg!(feature = "cm4") {
-            // CM4 dervived from HCLK
-            u64::from(us) * u64::from(self.clocks.hclk().raw() / 8_000_000)
-        } else {
-            // Normally divide by 8
-            u64::from(us) * u64::from(self.clocks.c_ck().raw() / 8_000_000)
-        };
+impl<'a, CLOCKS> PeriodicCountdown<'a, CLOCKS>
+where
+    CLOCKS: Clocks + TrustedClocks<'a>,
+{
+    /// Create a new periodic [CountDown] measured in microseconds.
+    pub fn new(syst: &'a mut SYST, clocks: CLOCKS) -> Self {
+        Self(Countdown::new(syst, clocks))
+    }
 
-        self.start_wait();
+    /// Starts counting down periods of `count`
+    pub fn start(&mut self, count: fugit::MicrosDurationU32) {
+        self.0.start(count);
     }
 
-    fn wait(&mut self) -> nb::Result<(), Infallible> {
-        if self.finished {
-            return Ok(());
-        }
+    /// Non-blockingly waits until the current period ends, the next one starts right away
+    pub fn wait(&mut self) -> nb::Result<(), Infallible> {
+        self.0.wait()?;
+        self.0.reload();
 
-        if self.syst.has_wrapped() {
-            self.syst.disable_counter();
-            self.start_wait();
-        }
+        Ok(())
+    }
+}
 
-        Err(nb::Error::WouldBlock)
+impl<'a, CLOCKS> CountDown for PeriodicCountdown<'a, CLOCKS>
+where
+    CLOCKS: Clocks + TrustedClocks<'a>,
+{
+    type Time = fugit::MicrosDurationU32;
+
+    fn start<T>(&mut self, count: T)
+    where
+        T: Into<Self::Time>,
+    {
+        PeriodicCountdown::start(self, count.into());
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Infallible> {
+        PeriodicCountdown::wait(self)
     }
 }
 
+impl<'a, CLOCKS> Periodic for PeriodicCountdown<'a, CLOCKS> where CLOCKS: Clocks + TrustedClocks<'a> {}
+
 impl<'a, CLOCKS> Delay<CLOCKS>
 where
     CLOCKS: Clocks + TrustedClocks<'a>,
@@ -135,26 +198,46 @@ where
     }
 }
 
-impl<'a, CLOCKS> DelayUs for Delay<CLOCKS>
+impl<'a, CLOCKS> DelayNs for Delay<CLOCKS>
 where
     CLOCKS: Clocks + TrustedClocks<'a>,
 {
+    /// Delays for at least `ns` nanoseconds
+    ///
+    /// Delays up to [`BUSY_LOOP_MAX_NS`] are busy-waited with [`cortex_m::asm::delay`], with the
+    /// cycle count computed from the HCLK of the current core. SysTick ticks at HCLK / 8, and its
+    /// setup alone takes longer than many sub-µs delays.
+    ///
+    /// The busy loop never returns early, but it can take longer than requested: interrupts
+    /// extend it, as does code executed from flash with wait states. For tight timings, run with
+    /// interrupts disabled and the ART accelerator enabled.
+    fn delay_ns(&mut self, ns: u32) {
+        if ns <= BUSY_LOOP_MAX_NS {
+            let hclk = c1_c2!(self.clocks.hclk1(), self.clocks.hclk2()).raw();
+            let cycles = (u64::from(ns) * u64::from(hclk)).div_ceil(1_000_000_000);
+
+            cortex_m::asm::delay(cycles as u32);
+        } else {
+            let total_rvr = systick_ticks(&self.clocks, u64::from(ns), 1_000_000_000);
+            self.delay_ticks(total_rvr);
+        }
+    }
+
     fn delay_us(&mut self, us: u32) {
-        // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
-        const MAX_RVR: u32 = 0x00FF_FFFF;
+        let total_rvr = systick_ticks(&self.clocks, u64::from(us), 1_000_000);
+        self.delay_ticks(total_rvr);
+    }
 
-        // With c_ck up to 480e6, we need u64 for delays > 8.9s
+    fn delay_ms(&mut self, ms: u32) {
+        let total_rvr = systick_ticks(&self.clocks, u64::from(ms), 1_000);
+        self.delay_ticks(total_rvr);
+    }
+}
 
-        let mut total_rvr = if cfg!(not(feature = "revision_v")) {
-            // See errata ES0392 §2.2.3. Revision Y does not have the /8 divider
-            u64::from(us) * u64::from(self.clocks.c_ck().raw() / 1_000_000)
-        } else if cfg!(feature = "cm4") {
-            // CM4 derived from HCLK
-            u64::from(us) * u64::from(self.clocks.hclk().raw() / 8_000_000)
-        } else {
-            // Normally divide by 8
-            u64::from(us) * u64::from(self.clocks.c_ck().raw() / 8_000_000)
-        };
+impl<CLOCKS> Delay<CLOCKS> {
+    fn delay_ticks(&mut self, mut total_rvr: u64) {
+        // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
+        const MAX_RVR: u32 = 0x00FF_FFFF;
 
         while total_rvr != 0 {
             let current_rvr = if total_rvr <= MAX_RVR.into() {
@@ -177,6 +260,20 @@ where
     }
 }
 
+/// Longest delay of [`Delay::delay_ns`] that is busy-waited instead of using SysTick
+pub const BUSY_LOOP_MAX_NS: u32 = 10_000;
+
+/// Number of SysTick ticks for `t` time units, with `units_per_sec` units per second
+///
+/// SysTick is clocked by the HCLK of the current core divided by 8. The result is rounded up,
+/// so the delay is never shorter than requested.
+fn systick_ticks(clocks: &impl Clocks, t: u64, units_per_sec: u64) -> u64 {
+    let hclk = c1_c2!(clocks.hclk1(), clocks.hclk2()).raw();
+
+    // With HCLK up to 64 MHz, we need u64 for delays > 2.1s
+    (t * u64::from(hclk / 8) + units_per_sec - 1) / units_per_sec
+}
+
 /// CountDown Timer as a delay provider
 pub struct DelayFromCountDownTimer<T>(T);
 
@@ -193,21 +290,20 @@ impl<T> DelayFromCountDownTimer<T> {
 }
 
 macro_rules! impl_delay_from_count_down_timer  {
-    ($(($Delay:ident, $delay:ident, $num:expr)),+) => {
-        $(
-
-            impl<T> $Delay for DelayFromCountDownTimer<T>
-            where
-                T: CountDown<Time = Hertz>,
-            {
+    ($(($delay:ident, $num:expr)),+) => {
+        impl<T> DelayNs for DelayFromCountDownTimer<T>
+        where
+            T: CountDown<Time = Hertz>,
+        {
+            $(
                 fn $delay(&mut self, t: u32) {
                     let mut time_left = t;
 
                     // Due to the LpTimer having only a 3 bit scaler, it is
                     // possible that the max timeout we can set is
                     // (128 * 65536) / clk_hz milliseconds.
-                    // Assuming the fastest clk_hz = 480Mhz this is roughly ~17ms,
-                    // or a frequency of ~57.2Hz. We use a 60Hz frequency for each
+                    // Assuming the fastest clk_hz = 64Mhz this is roughly ~131ms,
+                    // or a frequency of ~7.6Hz. We use a 60Hz frequency for each
                     // loop step here to ensure that we stay within these bounds.
                     let looping_delay = $num / 60;
                     let looping_delay_hz = Hertz::from_raw($num / looping_delay);
@@ -218,16 +314,20 @@ macro_rules! impl_delay_from_count_down_timer  {
                         time_left -= looping_delay;
                     }
 
+                    // Delays shorter than the timer resolution saturate at the
+                    // shortest period the timer supports
                     if time_left > 0 {
                         self.0.start(($num / time_left).Hz());
                         block!(self.0.wait()).ok();
                     }
                 }
-            }
-        )+
+            )+
+        }
     }
 }
 
 impl_delay_from_count_down_timer! {
-    (DelayUs, delay_us, 1_000_000)
+    (delay_ns, 1_000_000_000),
+    (delay_us, 1_000_000),
+    (delay_ms, 1_000)
 }
@@ -0,0 +1,416 @@
+//! DMA / DMAMUX
+
+use crate::pac::{dma1, dmamux1, DMA1, DMA2, DMAMUX1};
+use crate::rcc::rec;
+use core::marker::PhantomData;
+use core::sync::atomic::{compiler_fence, Ordering};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+/// DMAMUX request line (DMAREQ_ID), see RM0434 Rev 9 Table 41
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum DmaRequest {
+    /// No request, memory to memory transfers
+    None = 0,
+    ReqGen0 = 1,
+    ReqGen1 = 2,
+    ReqGen2 = 3,
+    ReqGen3 = 4,
+    Adc1 = 5,
+    Spi1Rx = 6,
+    Spi1Tx = 7,
+    Spi2Rx = 8,
+    Spi2Tx = 9,
+    I2c1Rx = 10,
+    I2c1Tx = 11,
+    I2c3Rx = 12,
+    I2c3Tx = 13,
+    Usart1Rx = 14,
+    Usart1Tx = 15,
+    Lpuart1Rx = 16,
+    Lpuart1Tx = 17,
+    Sai1A = 18,
+    Sai1B = 19,
+    Quadspi = 20,
+    Tim1Ch1 = 21,
+    Tim1Ch2 = 22,
+    Tim1Ch3 = 23,
+    Tim1Ch4 = 24,
+    Tim1Up = 25,
+    Tim1Trig = 26,
+    Tim1Com = 27,
+    Tim2Ch1 = 28,
+    Tim2Ch2 = 29,
+    Tim2Ch3 = 30,
+    Tim2Ch4 = 31,
+    Tim2Up = 32,
+    Tim16Ch1 = 33,
+    Tim16Up = 34,
+    Tim17Ch1 = 35,
+    Tim17Up = 36,
+    Aes1In = 37,
+    Aes1Out = 38,
+    Aes2In = 39,
+    Aes2Out = 40,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Bus error during the transfer (TEIF), the channel has been disabled by hardware
+    Transfer,
+}
+
+/// Buffer the DMA reads from
+///
+/// # Safety
+///
+/// The returned memory region must stay valid and must not be written to for the lifetime of `Self`
+pub unsafe trait ReadBuffer {
+    fn read_buffer(&self) -> (*const u8, usize);
+}
+
+/// Buffer the DMA writes to
+///
+/// # Safety
+///
+/// The returned memory region must stay valid and must not be accessed otherwise for the lifetime
+/// of `Self`
+pub unsafe trait WriteBuffer {
+    fn write_buffer(&mut self) -> (*mut u8, usize);
+}
+
+unsafe impl ReadBuffer for &'static [u8] {
+    fn read_buffer(&self) -> (*const u8, usize) {
+        (self.as_ptr(), self.len())
+    }
+}
+
+unsafe impl ReadBuffer for &'static mut [u8] {
+    fn read_buffer(&self) -> (*const u8, usize) {
+        (self.as_ptr(), self.len())
+    }
+}
+
+unsafe impl WriteBuffer for &'static mut [u8] {
+    fn write_buffer(&mut self) -> (*mut u8, usize) {
+        (self.as_mut_ptr(), self.len())
+    }
+}
+
+pub trait DmaExt {
+    type Channels;
+    type REC;
+
+    fn split(self, rec: &mut Self::REC, mux: &mut rec::DMAMUX1) -> Self::Channels;
+}
+
+/// DMA channel `C` of DMA controller `D`
+pub struct Channel<const D: u8, const C: u8> {
+    _marker: PhantomData<*const ()>,
+}
+
+pub struct Channels<const D: u8> {
+    pub c1: Channel<D, 1>,
+    pub c2: Channel<D, 2>,
+    pub c3: Channel<D, 3>,
+    pub c4: Channel<D, 4>,
+    pub c5: Channel<D, 5>,
+    pub c6: Channel<D, 6>,
+    pub c7: Channel<D, 7>,
+}
+
+impl<const D: u8> Channels<D> {
+    const fn new() -> Self {
+        Self {
+            c1: Channel::new(),
+            c2: Channel::new(),
+            c3: Channel::new(),
+            c4: Channel::new(),
+            c5: Channel::new(),
+            c6: Channel::new(),
+            c7: Channel::new(),
+        }
+    }
+}
+
+macro_rules! dma {
+    ($($DMAx:ident: $d:literal),* $(,)?) => {
+        $(
+            impl DmaExt for $DMAx {
+                type Channels = Channels<$d>;
+                type REC = rec::$DMAx;
+
+                fn split(self, rec: &mut Self::REC, mux: &mut rec::DMAMUX1) -> Channels<$d> {
+                    rec.enable();
+                    rec.reset();
+                    mux.enable();
+
+                    Channels::new()
+                }
+            }
+        )*
+    };
+}
+
+dma! {
+    DMA1: 1,
+    DMA2: 2,
+}
+
+struct Dma<const D: u8>;
+
+impl<const D: u8> Dma<D> {
+    const fn ptr() -> *const dma1::RegisterBlock {
+        match D {
+            1 => DMA1::PTR,
+            2 => DMA2::PTR as _,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Offset between the register sets of two consecutive channels
+const CHANNEL_STRIDE: usize = 0x14;
+
+/// Transfer direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    PeripheralToMemory,
+    MemoryToPeripheral,
+}
+
+impl<const D: u8, const C: u8> Channel<D, C> {
+    const fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+
+    fn regs() -> &'static dma1::RegisterBlock {
+        unsafe { &*Dma::<D>::ptr() }
+    }
+
+    /// Register of this channel, given the same register of channel 1
+    ///
+    /// All channels share the same layout, the PAC exposes them as distinct registers
+    fn ch_reg<T>(reg: &'static T) -> &'static T {
+        unsafe {
+            &*(reg as *const T)
+                .cast::<u8>()
+                .add(CHANNEL_STRIDE * (C as usize - 1))
+                .cast::<T>()
+        }
+    }
+
+    fn ccr() -> &'static dma1::CCR1 {
+        Self::ch_reg(&Self::regs().ccr1)
+    }
+
+    fn cndtr() -> &'static dma1::CNDTR1 {
+        Self::ch_reg(&Self::regs().cndtr1)
+    }
+
+    fn cpar() -> &'static dma1::CPAR1 {
+        Self::ch_reg(&Self::regs().cpar1)
+    }
+
+    fn cmar() -> &'static dma1::CMAR1 {
+        Self::ch_reg(&Self::regs().cmar1)
+    }
+
+    /// DMAMUX channel routed to this DMA channel
+    fn mux_cr() -> &'static dmamux1::C0CR {
+        let dmamux = unsafe { &*DMAMUX1::PTR };
+        let n = 7 * (D as usize - 1) + (C as usize - 1);
+
+        unsafe { &*(&dmamux.c0cr as *const dmamux1::C0CR).add(n) }
+    }
+
+    /// Interrupt flags (GIF, TCIF, HTIF, TEIF) of this channel
+    fn flags() -> u32 {
+        (Self::regs().isr.read().bits() >> (4 * (C as u32 - 1))) & 0xF
+    }
+
+    fn clear_flags(&mut self) {
+        Self::regs()
+            .ifcr
+            .write(|w| unsafe { w.bits(0xF << (4 * (C as u32 - 1))) });
+    }
+
+    fn set_request(&mut self, request: DmaRequest) {
+        Self::mux_cr().modify(|_, w| w.dmareq_id().variant(request.into()));
+    }
+
+    /// Programs a one-shot transfer of `len` bytes
+    ///
+    /// The channel is disabled first, CPAR, CMAR and CNDTR can only be written while EN is cleared
+    fn configure(&mut self, request: DmaRequest, dir: Direction, par: u32, mar: u32, len: u16) {
+        self.disable();
+        self.clear_flags();
+        self.set_request(request);
+
+        Self::cpar().write(|w| w.pa().variant(par));
+        Self::cmar().write(|w| w.ma().variant(mar));
+        Self::cndtr().write(|w| w.ndt().variant(len));
+
+        Self::ccr().write(|w| {
+            w.dir()
+                .bit(dir == Direction::MemoryToPeripheral)
+                .minc()
+                .set_bit()
+                .pinc()
+                .clear_bit()
+                .circ()
+                .clear_bit()
+                .mem2mem()
+                .clear_bit()
+                .psize()
+                .variant(0b00)
+                .msize()
+                .variant(0b00)
+                .tcie()
+                .set_bit()
+                .teie()
+                .set_bit()
+        });
+    }
+
+    fn enable(&mut self) {
+        // Make sure all memory accesses to the buffer are done before the DMA takes over
+        compiler_fence(Ordering::Release);
+
+        Self::ccr().modify(|_, w| w.en().set_bit());
+    }
+
+    fn disable(&mut self) {
+        Self::ccr().modify(|_, w| w.en().clear_bit());
+
+        compiler_fence(Ordering::Acquire);
+    }
+
+    /// Transfer complete flag (TCIF)
+    fn is_complete(&self) -> bool {
+        Self::flags() & 0b0010 != 0
+    }
+
+    /// Transfer error flag (TEIF)
+    fn has_error(&self) -> bool {
+        Self::flags() & 0b1000 != 0
+    }
+}
+
+/// One-shot DMA transfer
+///
+/// The transfer is programmed on creation and started with [`start`](Self::start). The channel
+/// and the buffer are handed back when the transfer is completed or aborted.
+pub struct Transfer<CH, BUF> {
+    channel: CH,
+    buf: BUF,
+}
+
+impl<const D: u8, const C: u8, BUF> Transfer<Channel<D, C>, BUF>
+where
+    BUF: ReadBuffer,
+{
+    /// Transfer from `buf` to the peripheral data register at address `par`, triggered by `request`
+    ///
+    /// # Safety
+    ///
+    /// `par` must be the address of a peripheral data register accepting byte writes
+    pub unsafe fn memory_to_peripheral(
+        mut channel: Channel<D, C>,
+        request: DmaRequest,
+        buf: BUF,
+        par: u32,
+    ) -> Self {
+        let (ptr, len) = buf.read_buffer();
+        assert!(len <= u16::MAX as usize);
+
+        channel.configure(
+            request,
+            Direction::MemoryToPeripheral,
+            par,
+            ptr as u32,
+            len as u16,
+        );
+
+        Self { channel, buf }
+    }
+}
+
+impl<const D: u8, const C: u8, BUF> Transfer<Channel<D, C>, BUF>
+where
+    BUF: WriteBuffer,
+{
+    /// Transfer from the peripheral data register at address `par` into `buf`, triggered by
+    /// `request`
+    ///
+    /// # Safety
+    ///
+    /// `par` must be the address of a peripheral data register supporting byte reads
+    pub unsafe fn peripheral_to_memory(
+        mut channel: Channel<D, C>,
+        request: DmaRequest,
+        mut buf: BUF,
+        par: u32,
+    ) -> Self {
+        let (ptr, len) = buf.write_buffer();
+        assert!(len <= u16::MAX as usize);
+
+        channel.configure(
+            request,
+            Direction::PeripheralToMemory,
+            par,
+            ptr as u32,
+            len as u16,
+        );
+
+        Self { channel, buf }
+    }
+}
+
+impl<const D: u8, const C: u8, BUF> Transfer<Channel<D, C>, BUF> {
+    /// Enables the channel, the transfer proceeds on each request of the peripheral
+    pub fn start(&mut self) {
+        self.channel.enable();
+    }
+
+    /// The DMA has moved all bytes (TCIF)
+    pub fn is_complete(&self) -> bool {
+        self.channel.is_complete()
+    }
+
+    /// A bus error occured (TEIF)
+    pub fn has_error(&self) -> bool {
+        self.channel.has_error()
+    }
+
+    /// Remaining number of bytes to transfer (CNDTR)
+    pub fn remaining(&self) -> u16 {
+        Channel::<D, C>::cndtr().read().ndt().bits()
+    }
+
+    /// Stops the transfer and releases the channel and the buffer
+    pub fn abort(mut self) -> (Channel<D, C>, BUF) {
+        self.channel.disable();
+        self.channel.clear_flags();
+
+        (self.channel, self.buf)
+    }
+
+    /// Blocks until the transfer is completed, then releases the channel and the buffer
+    ///
+    /// On a transfer error, the channel and the buffer are handed back with the error
+    pub fn wait(self) -> Result<(Channel<D, C>, BUF), (Error, Channel<D, C>, BUF)> {
+        while !self.is_complete() && !self.has_error() {}
+
+        let error = self.has_error();
+        let (channel, buf) = self.abort();
+
+        if error {
+            Err((Error::Transfer, channel, buf))
+        } else {
+            Ok((channel, buf))
+        }
+    }
+}
@@ -1,6 +1,8 @@
+use crate::crc::Crc;
 use crate::signature::FlashSize;
 use crate::time::Hertz;
 use crate::{pac::FLASH, pwr::Vos};
+use core::ops::Range;
 use fugit::RateExtU32;
 use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
 
@@ -11,11 +13,15 @@ pub const PAGE_SIZE: usize = 0x0000_1000;
 /// See RM0434 Rev 9 p.75
 pub const FLASH_BASE_ADDR: usize = 0x0800_0000;
 
+/// SRAM1, SRAM2a and SRAM2b
+#[cfg(feature = "cm0p")]
+const SRAM_RANGE: Range<usize> = 0x2000_0000..0x2004_0000;
+
 pub fn flash_end() -> usize {
     FLASH_BASE_ADDR + FlashSize::get().bytes() - 1
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct FlashUid {
     // 0x1FFF_7580
@@ -44,6 +50,19 @@ impl FlashUid {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for FlashUid {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "FlashUid {{ uid: {=u32:#010x}, dev_id: {=u8:#04x}, manufacturer: {=u32:#08x} }}",
+            self.uid(),
+            self.dev_id(),
+            self.manufacturer(),
+        )
+    }
+}
+
 pub enum Error {
     /// Program / Erase operation suspended (PESD)
     OperationSuspended,
@@ -51,6 +70,15 @@ pub enum Error {
     SecureFlashError,
     /// Error with custom status
     Status(Status),
+    /// Write outside of the allowed range
+    OutOfBounds,
+    /// The checksum of the programmed data doesn't match
+    VerifyFailed,
+    /// The flash was still busy after the timeout, see [`Flash::wait_while_busy_timeout`]
+    Timeout,
+    /// RDP Level 2 was requested without confirming the irreversible transition
+    #[cfg(feature = "flash_rdp_l2")]
+    RdpL2NotConfirmed,
 }
 
 pub enum ConfigError {
@@ -95,17 +123,21 @@ pub trait FlashExt {
 
 impl FlashExt for FLASH {
     fn constrain(self) -> Flash {
-        Flash { flash: self }
+        Flash::new(self)
     }
 }
 
 pub struct Flash {
     flash: FLASH,
+    options_pending: bool,
 }
 
 impl Flash {
     pub fn new(flash: FLASH) -> Self {
-        Self { flash }
+        Self {
+            flash,
+            options_pending: false,
+        }
     }
 
     pub const fn address(&self) -> usize {
@@ -116,6 +148,27 @@ impl Flash {
         FlashSize::get().bytes()
     }
 
+    /// Checksum of `len` bytes at `offset` relative to the flash base address
+    ///
+    /// The region is fed through `crc` in words, see [`Crc::feed`]. The calculation is
+    /// restarted first. Fails with [`Error::OutOfBounds`] if the region exceeds the flash.
+    pub fn crc_region(&self, crc: &mut Crc, offset: usize, len: usize) -> Result<u32, Error> {
+        let end = offset.checked_add(len).ok_or(Error::OutOfBounds)?;
+
+        if end > self.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        // SAFETY: The region is in bounds of flash
+        let region =
+            unsafe { core::slice::from_raw_parts((self.address() + offset) as *const u8, len) };
+
+        crc.reset();
+        crc.feed(region);
+
+        Ok(crc.result())
+    }
+
     /// Unlocks the Flash and returns a handle to the unlocked flash
     ///
     /// The Flash is locked automatically after dropping the handle
@@ -173,10 +226,106 @@ impl Flash {
         FlashUid::get().uid64()
     }
 
+    /// Page is inside a WRP area or overlaps a PCROP area, programming or erasing it fails
+    pub fn is_write_protected(&self, page: Page) -> bool {
+        let wrp1ar = self.flash.wrp1ar.read();
+        let wrp1br = self.flash.wrp1br.read();
+        let p = u8::from(page);
+
+        area_contains(wrp1ar.wrp1a_strt().bits(), wrp1ar.wrp1a_end().bits(), p)
+            || area_contains(wrp1br.wrp1b_strt().bits(), wrp1br.wrp1b_end().bits(), p)
+            || self.is_pcrop(HalfPage(p as u16 * 2))
+            || self.is_pcrop(HalfPage(p as u16 * 2 + 1))
+    }
+
+    /// Half page is inside a PCROP area
+    pub fn is_pcrop(&self, hp: HalfPage) -> bool {
+        let hp = u16::from(hp);
+
+        area_contains(
+            self.flash.pcrop1asr.read().pcrop1a_strt().bits(),
+            self.flash.pcrop1aer.read().pcrop1a_end().bits(),
+            hp,
+        ) || area_contains(
+            self.flash.pcrop1bsr.read().pcrop1b_strt().bits(),
+            self.flash.pcrop1ber.read().pcrop1b_end().bits(),
+            hp,
+        )
+    }
+
+    /// Reloads the option bytes
+    ///
+    /// # Note
+    ///
+    /// Launching the option byte loading **resets the MCU immediately**, this function
+    /// effectively never returns. Make sure all option bytes are written before calling it.
+    /// Prefer [`Flash::reload_option_bytes_and_reset`], which makes the reset explicit.
     pub fn load_option_bytes(&mut self) {
-        while self.flash.sr.read().bsy().bit_is_set() {}
+        self.reload_option_bytes_and_reset();
+    }
+
+    /// Modified option bytes have been programmed, but are not loaded yet
+    ///
+    /// The new values take effect after [`Flash::reload_option_bytes_and_reset`] or a power-on reset.
+    pub fn option_bytes_pending_reload(&self) -> bool {
+        self.options_pending
+    }
+
+    /// Reloads the option bytes, which resets the MCU
+    ///
+    /// Waits for all pending flash and option operations to complete before the reload is launched.
+    //
+    // See RM0434 Rev 9 p. 97
+    pub fn reload_option_bytes_and_reset(&mut self) -> ! {
+        self.wait_while_busy();
+
+        // OBL_LAUNCH can only be set while both the flash and the options are unlocked
+        if self.flash.cr.read().lock().bit_is_set() {
+            unlock(&self.flash);
+        }
+
+        if self.flash.cr.read().optlock().bit_is_set() {
+            unlock_options(&self.flash);
+        }
 
         self.flash.cr.modify(|_, w| w.obl_launch().set_bit());
+
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+
+    /// Waits until no flash operation is ongoing (BSY)
+    fn wait_while_busy(&self) {
+        let sr = &c1_c2!(self.flash.sr, self.flash.c2sr);
+
+        while sr.read().bsy().bit_is_set() {}
+    }
+
+    /// Like [`wait_while_busy`](Self::wait_while_busy), but polls BSY at most `polls` times
+    ///
+    /// Fails with [`Error::Timeout`] if the flash is still busy, e.g. because the controller
+    /// hangs. Size `polls` for the longest operation, see the page erase time in the datasheet.
+    pub fn wait_while_busy_timeout(&self, polls: u32) -> Result<(), Error> {
+        let sr = &c1_c2!(self.flash.sr, self.flash.c2sr);
+
+        if (0..polls).any(|_| sr.read().bsy().bit_is_clear()) {
+            Ok(())
+        } else {
+            Err(Error::Timeout)
+        }
+    }
+
+    /// [`wait_while_busy_timeout`](Self::wait_while_busy_timeout) if `polls` is given, otherwise
+    /// [`wait_while_busy`](Self::wait_while_busy)
+    fn wait_while_busy_polls(&self, polls: Option<u32>) -> Result<(), Error> {
+        match polls {
+            Some(polls) => self.wait_while_busy_timeout(polls),
+            None => {
+                self.wait_while_busy();
+                Ok(())
+            }
+        }
     }
 
     pub fn prefetch_enable(&mut self, en: bool) {
@@ -227,10 +376,35 @@ impl Flash {
         acr.modify(|_, w| w.pes().bit(suspend));
     }
 
+    #[deprecated(note = "This is the EMPTY boot flag, not the erase state, use `boot_empty_flag`")]
     pub fn is_empty(&self) -> bool {
+        self.boot_empty_flag()
+    }
+
+    /// Flash user area empty flag (EMPTY)
+    ///
+    /// Set by the bootloader when the first location of the user flash is erased, which makes
+    /// the device boot from system memory. This doesn't tell whether any other region is erased,
+    /// see [`region_is_erased`](Self::region_is_erased).
+    pub fn boot_empty_flag(&self) -> bool {
         self.flash.acr.read().empty().bit()
     }
 
+    /// All `len` bytes at `offset` relative to the flash base address are erased (0xFF)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region exceeds the flash
+    pub fn region_is_erased(&self, offset: usize, len: usize) -> bool {
+        assert!(offset.checked_add(len).is_some_and(|end| end <= self.len()));
+
+        // SAFETY: The region is in bounds of flash
+        let region =
+            unsafe { core::slice::from_raw_parts((self.address() + offset) as *const u8, len) };
+
+        region.iter().all(|&b| b == 0xFF)
+    }
+
     pub fn latency(&self) -> Latency {
         self.flash.acr.read().latency().bits().try_into().unwrap()
     }
@@ -249,6 +423,22 @@ impl Drop for UnlockedFlash<'_> {
     }
 }
 
+/// Panics unless the calling function is executed from SRAM
+///
+/// Code fetched from flash stalls the CPU as soon as the flash goes busy, this turns the lock-up
+/// into a panic.
+#[cfg(feature = "cm0p")]
+#[inline(always)]
+fn assert_running_from_sram() {
+    let pc = cortex_m::register::pc::read() as usize;
+
+    assert!(
+        SRAM_RANGE.contains(&pc),
+        "must be executed from SRAM, PC = {:#010x}",
+        pc
+    );
+}
+
 impl<'a> UnlockedFlash<'a> {
     fn reg(&self) -> &FLASH {
         &self.flash.flash
@@ -266,10 +456,24 @@ impl<'a> UnlockedFlash<'a> {
     //
     // See RM0434 Rev9 p. 82
     pub unsafe fn page_erase(&mut self, page: u8) -> Result<(), Error> {
+        self.page_erase_polls(page, None)
+    }
+
+    /// Like [`page_erase`](Self::page_erase), but fails with [`Error::Timeout`] if the flash is
+    /// still busy after `polls` polls, see [`Flash::wait_while_busy_timeout`]
+    ///
+    /// # Safety
+    ///
+    /// Make sure you don't erase your code
+    pub unsafe fn page_erase_timeout(&mut self, page: u8, polls: u32) -> Result<(), Error> {
+        self.page_erase_polls(page, Some(polls))
+    }
+
+    unsafe fn page_erase_polls(&mut self, page: u8, polls: Option<u32>) -> Result<(), Error> {
         let sr = &c1_c2!(self.reg().sr, self.reg().c2sr);
         let cr = &c1_c2!(self.reg().cr, self.reg().c2cr);
 
-        while sr.read().bsy().bit_is_set() {}
+        self.flash.wait_while_busy_polls(polls)?;
 
         if sr.read().pesd().bit_is_set() {
             return Err(Error::OperationSuspended);
@@ -299,9 +503,21 @@ impl<'a> UnlockedFlash<'a> {
                 .set_bit()
         });
 
-        while sr.read().bsy().bit_is_set() {}
+        let res = self.flash.wait_while_busy_polls(polls);
 
-        Ok(())
+        cr.modify(|_, w| w.per().clear_bit());
+
+        res
+    }
+
+    /// Like [`page_erase`](Self::page_erase), with interrupts disabled, see
+    /// [`program_critical`](Self::program_critical)
+    ///
+    /// # Safety
+    ///
+    /// Make sure you don't erase your code
+    pub unsafe fn page_erase_critical(&mut self, page: u8) -> Result<(), Error> {
+        cortex_m::interrupt::free(|_| self.page_erase(page))
     }
 
     /// CPU2: Complete flash will be wiped
@@ -313,7 +529,25 @@ impl<'a> UnlockedFlash<'a> {
     // See RM0434 Rev 9 p. 83
     #[cfg(feature = "cm0p")]
     pub unsafe fn mass_erase(&mut self) -> Result<(), Error> {
-        while self.flash.flash.c2sr.read().bsy().bit_is_set() {}
+        self.mass_erase_polls(None)
+    }
+
+    /// Like [`mass_erase`](Self::mass_erase), but fails with [`Error::Timeout`] if the flash is
+    /// still busy after `polls` polls, see [`Flash::wait_while_busy_timeout`]
+    ///
+    /// # SAFETY
+    ///
+    /// This must be executed from SRAM
+    #[cfg(feature = "cm0p")]
+    pub unsafe fn mass_erase_timeout(&mut self, polls: u32) -> Result<(), Error> {
+        self.mass_erase_polls(Some(polls))
+    }
+
+    #[cfg(feature = "cm0p")]
+    unsafe fn mass_erase_polls(&mut self, polls: Option<u32>) -> Result<(), Error> {
+        assert_running_from_sram();
+
+        self.flash.wait_while_busy_polls(polls)?;
 
         self.clear_sr();
 
@@ -336,22 +570,71 @@ impl<'a> UnlockedFlash<'a> {
                 .set_bit()
         });
 
-        while self.flash.flash.c2sr.read().bsy().bit_is_set() {}
+        let res = self.flash.wait_while_busy_polls(polls);
 
-        Ok(())
+        self.flash.flash.c2cr.modify(|_, w| w.mer().clear_bit());
+
+        res
     }
 
     /// Normal programming of data into flash
     ///
     /// - `offset` must be multiple of 8
     /// - size of `data` must be multiple of 64 bits
+    ///
+    /// **Warning:** `offset` is relative to the flash base address and isn't checked against
+    /// anything else, including the application's own code. Use
+    /// [`program_checked`](Self::program_checked) to restrict writes to a known region.
     //
     // See RM0434 Rev 9 p. 84
     pub fn program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        self.program_polls(offset, data, None)
+    }
+
+    /// Like [`program`](Self::program), but fails with [`Error::Timeout`] if the flash is still
+    /// busy after `polls` polls for any double word, see [`Flash::wait_while_busy_timeout`]
+    pub fn program_timeout(&mut self, offset: usize, data: &[u8], polls: u32) -> Result<(), Error> {
+        self.program_polls(offset, data, Some(polls))
+    }
+
+    fn program_polls(
+        &mut self,
+        offset: usize,
+        data: &[u8],
+        polls: Option<u32>,
+    ) -> Result<(), Error> {
         if data.len() % 8 != 0 || offset % 8 != 0 {
             panic!("Size of `data` and offset must be a multiple of 64 bit");
         }
 
+        self.program_double_words(
+            offset,
+            data.chunks_exact(8)
+                .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())),
+            polls,
+        )
+    }
+
+    /// Like [`program`](Self::program), writing 64 bit words directly
+    ///
+    /// - `offset` must be multiple of 8
+    ///
+    /// The words are stored in the native (little endian) byte order, so reading the flash back
+    /// as `u64` yields `words`.
+    pub fn program_words(&mut self, offset: usize, words: &[u64]) -> Result<(), Error> {
+        if offset % 8 != 0 {
+            panic!("Offset must be a multiple of 64 bit");
+        }
+
+        self.program_double_words(offset, words.iter().copied(), None)
+    }
+
+    fn program_double_words(
+        &mut self,
+        offset: usize,
+        words: impl Iterator<Item = u64>,
+        polls: Option<u32>,
+    ) -> Result<(), Error> {
         self.clear_sr();
 
         let cr = &c1_c2!(self.reg().cr, self.reg().c2cr);
@@ -378,29 +661,88 @@ impl<'a> UnlockedFlash<'a> {
         // offset / 4 bytes
         ptr = unsafe { ptr.add(offset >> 2) };
 
-        for chunk in data.chunks_exact(8) {
-            let w1 = u32::from_le_bytes(chunk[0..4].try_into().unwrap());
-            let w2 = u32::from_le_bytes(chunk[4..].try_into().unwrap());
+        let program = || -> Result<(), Error> {
+            for word in words {
+                let w1 = word as u32;
+                let w2 = (word >> 32) as u32;
+
+                // SAFETY: RM0434 Rev 9 p. 84 - Standard Programming - Step 4
+                unsafe {
+                    core::ptr::write_volatile(ptr, w1);
+                    ptr = ptr.add(1);
+                    core::ptr::write_volatile(ptr, w2);
+                    ptr = ptr.add(1);
+                }
+
+                self.flash.wait_while_busy_polls(polls)?;
 
-            // SAFETY: RM0434 Rev 9 p. 84 - Standard Programming - Step 4
-            unsafe {
-                core::ptr::write_volatile(ptr, w1);
-                ptr = ptr.add(1);
-                core::ptr::write_volatile(ptr, w2);
-                ptr = ptr.add(1);
+                if sr.read().eop().bit_is_set() {
+                    sr.modify(|_, w| w.eop().clear_bit());
+                } else {
+                    return Err(Error::Status(Status { r: sr.read() }));
+                }
             }
 
-            while sr.read().bsy().bit_is_set() {}
+            Ok(())
+        };
 
-            if sr.read().eop().bit_is_set() {
-                sr.modify(|_, w| w.eop().clear_bit());
-            } else {
-                return Err(Error::Status(Status { r: sr.read() }));
-            }
-        }
+        let res = program();
 
+        // Leave programming mode on errors as well, so the flash can be used again
         cr.modify(|_, w| w.pg().clear_bit());
 
+        res
+    }
+
+    /// Like [`program`](Self::program), with interrupts disabled
+    ///
+    /// The flash can't be read while it's programmed, an interrupt handler executing from or
+    /// reading the flash stalls the CPU until the operation is done. Disabling interrupts keeps
+    /// the latency of the handlers predictable, but doesn't make the flash readable: handlers
+    /// still run late, once the critical section is left.
+    pub fn program_critical(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        cortex_m::interrupt::free(|_| self.program(offset, data))
+    }
+
+    /// Like [`program`](Self::program), but rejects writes not fully contained in `allowed`
+    ///
+    /// `allowed` is a range of offsets relative to the flash base address. Fails with
+    /// [`Error::OutOfBounds`] without touching the flash otherwise.
+    pub fn program_checked(
+        &mut self,
+        offset: usize,
+        data: &[u8],
+        allowed: Range<usize>,
+    ) -> Result<(), Error> {
+        let end = offset.checked_add(data.len()).ok_or(Error::OutOfBounds)?;
+
+        if offset < allowed.start || end > allowed.end {
+            return Err(Error::OutOfBounds);
+        }
+
+        self.program(offset, data)
+    }
+
+    /// Like [`program`](Self::program), then compares the checksum of the written region with
+    /// the one of `data`
+    ///
+    /// Fails with [`Error::VerifyFailed`] on a mismatch.
+    pub fn program_and_verify(
+        &mut self,
+        crc: &mut Crc,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.program(offset, data)?;
+
+        crc.reset();
+        crc.feed(data);
+        let expected = crc.result();
+
+        if self.flash.crc_region(crc, offset, data.len())? != expected {
+            return Err(Error::VerifyFailed);
+        }
+
         Ok(())
     }
 
@@ -417,13 +759,43 @@ impl<'a> UnlockedFlash<'a> {
     // See RM0434 Rev 9 p. 85
     #[cfg(feature = "cm0p")]
     pub unsafe fn fast_program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        self.fast_program_polls(offset, data, None)
+    }
+
+    /// Like [`fast_program`](Self::fast_program), but fails with [`Error::Timeout`] if the flash
+    /// is still busy after `polls` polls, see [`Flash::wait_while_busy_timeout`]
+    ///
+    /// `polls` bounds each wait separately, including the one for the mass erase.
+    ///
+    /// # SAFETY
+    ///
+    /// This must be executed from SRAM
+    #[cfg(feature = "cm0p")]
+    pub unsafe fn fast_program_timeout(
+        &mut self,
+        offset: usize,
+        data: &[u8],
+        polls: u32,
+    ) -> Result<(), Error> {
+        self.fast_program_polls(offset, data, Some(polls))
+    }
+
+    #[cfg(feature = "cm0p")]
+    unsafe fn fast_program_polls(
+        &mut self,
+        offset: usize,
+        data: &[u8],
+        polls: Option<u32>,
+    ) -> Result<(), Error> {
+        assert_running_from_sram();
+
         if data.len() % 512 != 0 || offset % 512 != 0 {
             panic!("Size of `data` and offset must be a multiple of 512 Bytes");
         }
 
-        self.mass_erase()?;
+        self.mass_erase_polls(polls)?;
 
-        while self.flash.flash.c2sr.read().bsy().bit_is_set() {}
+        self.flash.wait_while_busy_polls(polls)?;
 
         self.clear_sr();
 
@@ -447,35 +819,46 @@ impl<'a> UnlockedFlash<'a> {
         // offset / 4 bytes
         ptr = ptr.add(offset >> 2);
 
-        for chunk in data.chunks_exact(512) {
-            for word in chunk
-                .chunks_exact(4)
-                .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
-            {
-                core::ptr::write_volatile(ptr, word);
-                ptr = ptr.add(1);
-            }
+        let program = || -> Result<(), Error> {
+            for chunk in data.chunks_exact(512) {
+                for word in chunk
+                    .chunks_exact(4)
+                    .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+                {
+                    core::ptr::write_volatile(ptr, word);
+                    ptr = ptr.add(1);
+                }
 
-            while self.flash.flash.c2sr.read().bsy().bit_is_set() {}
+                self.flash.wait_while_busy_polls(polls)?;
 
-            if self.flash.flash.c2sr.read().eop().bit_is_set() {
-                self.flash.flash.c2sr.modify(|_, w| w.eop().clear_bit());
-            } else {
-                return Err(Error::Status(Status {
-                    r: self.flash.flash.c2sr.read(),
-                }));
+                if self.flash.flash.c2sr.read().eop().bit_is_set() {
+                    self.flash.flash.c2sr.modify(|_, w| w.eop().clear_bit());
+                } else {
+                    return Err(Error::Status(Status {
+                        r: self.flash.flash.c2sr.read(),
+                    }));
+                }
             }
-        }
 
+            Ok(())
+        };
+
+        let res = program();
+
+        // Leave fast programming mode on errors as well, so the flash can be used again
         self.flash.flash.c2cr.modify(|_, w| w.fstpg().clear_bit());
 
-        Ok(())
+        res
     }
 
+    /// Unlocks the option registers, see [`OptionsUnlocked`]
     pub fn options_unlocked(&mut self) -> OptionsUnlocked<'_, 'a> {
         unlock_options(&self.flash.flash);
 
-        OptionsUnlocked { flash: self }
+        OptionsUnlocked {
+            flash: self,
+            modified: false,
+        }
     }
 
     /*#[cfg(feature = "cm4")]
@@ -490,8 +873,15 @@ impl<'a> UnlockedFlash<'a> {
     }
 }
 
+/// Unlocked option registers
+///
+/// Changes are only programmed into the option bytes by [`commit`](Self::commit). Dropping
+/// without committing locks the options again and discards the uncommitted changes, the option
+/// registers are restored from the option bytes on the next option byte reload.
 pub struct OptionsUnlocked<'a, 'b> {
     flash: &'a mut UnlockedFlash<'b>,
+    /// An option register was written, the option bytes have to be programmed
+    modified: bool,
 }
 
 impl Drop for OptionsUnlocked<'_, '_> {
@@ -501,14 +891,77 @@ impl Drop for OptionsUnlocked<'_, '_> {
 }
 
 impl OptionsUnlocked<'_, '_> {
-    fn reg(&self) -> &FLASH {
+    /// Option register to write, the option bytes are programmed by `commit`
+    fn reg(&mut self) -> &FLASH {
+        self.modified = true;
         self.flash.reg()
     }
 
+    /// Programs the modified option bytes and locks the options
+    ///
+    /// Nothing is programmed if no option was changed. Fails with [`Error::Status`] if the
+    /// programming failed.
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.program()
+    }
+
+    /// Programs the modified option bytes, see RM0434 Rev 9 p. 97
+    fn program(&mut self) -> Result<(), Error> {
+        if !self.modified {
+            return Ok(());
+        }
+
+        self.modified = false;
+        self.flash.clear_sr();
+
+        let flash = &mut *self.flash.flash;
+
+        flash.wait_while_busy();
+        flash.flash.cr.modify(|_, w| w.optstrt().set_bit());
+        flash.wait_while_busy();
+
+        let status = Status {
+            r: c1_c2!(flash.flash.sr.read(), flash.flash.c2sr.read()),
+        };
+
+        let optverr = c1_c2!(status.r.optverr().bit_is_set(), false);
+
+        if status.prog_err() || optverr {
+            return Err(Error::Status(status));
+        }
+
+        flash.options_pending = true;
+
+        Ok(())
+    }
+
+    /// Writes the RDP level without any check, prefer [`set_rdp`](Self::set_rdp)
     pub fn read_protection(&mut self, rdp: RdpLevel) {
         self.reg().optr.modify(|_, w| w.rdp().variant(rdp.into()));
     }
 
+    /// Sets the read protection level
+    ///
+    /// # Note
+    ///
+    /// - Going from Level 1 to Level 0 triggers a **mass erase** of the flash main memory when
+    /// the option bytes are loaded
+    /// - Going to Level 2 is **irreversible**, see [`RdpLevel`]. It's refused with
+    /// [`Error::RdpL2NotConfirmed`] unless `confirm_irreversible` is `true`
+    pub fn set_rdp(&mut self, level: RdpLevel, confirm_irreversible: bool) -> Result<(), Error> {
+        #[cfg(feature = "flash_rdp_l2")]
+        if level == RdpLevel::L2 && !confirm_irreversible {
+            return Err(Error::RdpL2NotConfirmed);
+        }
+
+        #[cfg(not(feature = "flash_rdp_l2"))]
+        let _ = confirm_irreversible;
+
+        self.read_protection(level);
+
+        Ok(())
+    }
+
     pub fn system_security_enabled(&mut self, en: bool) {
         self.reg().optr.modify(|_, w| w.ese().bit(en));
     }
@@ -683,6 +1136,13 @@ impl OptionsUnlocked<'_, '_> {
     }
 }
 
+/// Protection area from `start` to `end`, both inclusive, contains `x`
+///
+/// The area is disabled when `start > end`
+fn area_contains<T: PartialOrd>(start: T, end: T, x: T) -> bool {
+    start <= end && start <= x && x <= end
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Cpu2ResetMemory {
     Sram,
@@ -698,6 +1158,25 @@ impl HalfPage {
 
         Self(hp)
     }
+
+    /// Half page size in Bytes (2 KiB)
+    pub const SIZE: usize = PAGE_SIZE / 2;
+
+    /// Address range covered by this half page
+    pub fn address_range(&self) -> Range<usize> {
+        let start = FLASH_BASE_ADDR + self.0 as usize * Self::SIZE;
+
+        start..start + Self::SIZE
+    }
+
+    /// Half page containing `addr`, `None` if `addr` is outside the flash memory
+    pub fn from_address(addr: usize) -> Option<Self> {
+        if !(FLASH_BASE_ADDR..=flash_end()).contains(&addr) {
+            return None;
+        }
+
+        Some(Self(((addr - FLASH_BASE_ADDR) / Self::SIZE) as u16))
+    }
 }
 
 impl From<HalfPage> for u16 {
@@ -713,6 +1192,22 @@ impl Page {
     pub fn new(p: u8) -> Self {
         Self(p)
     }
+
+    /// Address range covered by this page
+    pub fn address_range(&self) -> Range<usize> {
+        let start = FLASH_BASE_ADDR + self.0 as usize * PAGE_SIZE;
+
+        start..start + PAGE_SIZE
+    }
+
+    /// Page containing `addr`, `None` if `addr` is outside the flash memory
+    pub fn from_address(addr: usize) -> Option<Self> {
+        if !(FLASH_BASE_ADDR..=flash_end()).contains(&addr) {
+            return None;
+        }
+
+        Some(Self(((addr - FLASH_BASE_ADDR) / PAGE_SIZE) as u8))
+    }
 }
 
 impl From<Page> for u8 {
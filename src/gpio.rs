@@ -1,7 +1,8 @@
 //! GPIO
 
-// pub mod alt;
+pub mod alt;
 pub mod convert;
+pub mod erased;
 
 use core::convert::Infallible;
 use core::marker::PhantomData;
@@ -43,6 +44,8 @@ mod marker {
     /// Marker trait for all pin modes except alternate
     pub trait NotAlt {}
     /// Marker trait for pins with alternate function `A` mapping
+    ///
+    /// Implemented from the per-pin AF lists of the `gpio!` invocations
     pub trait IntoAf<const A: u8> {}
 }
 
@@ -59,7 +62,12 @@ impl marker::NotAlt for Input {}
 impl<OType> marker::NotAlt for Output<OType> {}
 impl marker::NotAlt for Analog {}
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, TryFromPrimitive, IntoPrimitive)]
+/// Output speed (OSPEEDR)
+///
+/// The maximum frequency of each setting depends on V_{DD} and the load capacitance, see the I/O
+/// AC characteristics of the datasheet. Higher speeds steepen the edges, which increases the
+/// consumption and the EMI, so the lowest speed fitting the signal should be selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum Speed {
     Low = 0b00,
@@ -115,6 +123,15 @@ pub trait GpioExt {
     type REC;
 
     fn split(self, rec: &mut Self::REC) -> Self::Parts;
+
+    /// Like [`split`](Self::split), but keeps the current configuration of the port
+    ///
+    /// The port clock is enabled without resetting its registers, so pins configured before,
+    /// e.g. by a bootloader driving a regulator enable pin, don't glitch.
+    ///
+    /// The type states of the pins are the same as with [`split`](Self::split) and don't reflect
+    /// the actual configuration. Convert each pin into the mode it's used in before using it.
+    fn split_without_reset(self, rec: &mut Self::REC) -> Self::Parts;
 }
 
 pub struct Pin<const P: char, const N: u8, MODE = Analog> {
@@ -122,6 +139,11 @@ pub struct Pin<const P: char, const N: u8, MODE = Analog> {
 }
 
 impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
+    /// Port of the pin, e.g. `'A'` for PA5
+    pub const PORT: char = P;
+    /// Number of the pin within its port, e.g. `5` for PA5
+    pub const NUMBER: u8 = N;
+
     const fn new() -> Self {
         Self { _mode: PhantomData }
     }
@@ -199,12 +221,12 @@ macro_rules! n_reg_w {
     }};
 }
 
-macro_rules! n_reg_r_bit {
+macro_rules! n_reg_r {
     ($n:expr, $r:expr, $field:ident) => {{
         macro_rules! __n_reg {
             ($nn:literal) => {{
                 paste! {
-                    $r.[<$field $nn>]().bit()
+                    $r.[<$field $nn>]().bits()
                 }
             }};
         }
@@ -217,6 +239,7 @@ impl<const P: char, const N: u8, MODE> Pin<P, N, MODE>
 where
     MODE: marker::OutputSpeed,
 {
+    /// Sets the output speed, see [`Speed`]
     pub fn set_speed(&mut self, speed: Speed) {
         unsafe {
             (*Gpio::<P>::ptr())
@@ -225,11 +248,35 @@ where
         }
     }
 
+    /// Like [`set_speed`](Self::set_speed), taking and returning the pin
     #[inline(always)]
-    pub fn speed(mut self, speed: Speed) -> Self {
+    pub fn with_speed(mut self, speed: Speed) -> Self {
         self.set_speed(speed);
         self
     }
+
+    /// Reads the currently configured speed back from OSPEEDR
+    pub fn speed(&self) -> Speed {
+        let bits = unsafe {
+            let r = (*Gpio::<P>::ptr()).ospeedr.read();
+
+            n_reg_r!(N, r, ospeedr)
+        };
+
+        bits.try_into().unwrap()
+    }
+}
+
+/// Whether PWR PUCRx / PDCRx has a control bit for `pull` on pin `N` of port `P`
+///
+/// Besides the pins missing on ports E and H, a few bits are reserved on ports A and B.
+const fn lp_resistor_available(p: char, n: u8, pull: Pull) -> bool {
+    erased::pin_exists(p, n)
+        && match (pull, p, n) {
+            (Pull::Up, 'A', 14) => false,
+            (Pull::Down, 'A', 13 | 15) | (Pull::Down, 'B', 4) => false,
+            _ => true,
+        }
 }
 
 impl<const P: char, const N: u8, MODE> Pin<P, N, MODE>
@@ -244,7 +291,20 @@ where
         }
     }
 
+    /// Configures the pull applied in Standby / Shutdown mode (PWR PUCRx / PDCRx)
+    ///
+    /// Takes effect once `apply_pull_mode_cfg` is set in PWR.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pull is not available on this pin: PA14 has no pull-up, and PA13, PA15 and PB4
+    /// have no pull-down in low-power mode.
     pub fn set_internal_resistor_lp(&mut self, resistor: Pull) {
+        assert!(
+            lp_resistor_available(P, N, resistor),
+            "pull not available in low-power mode on this pin"
+        );
+
         let pwr = unsafe { &*crate::pac::PWR::PTR };
 
         let (pu, pd) = match resistor {
@@ -285,6 +345,61 @@ where
         self.set_internal_resistor(resistor);
         self
     }
+
+    /// See [`set_internal_resistor_lp`](Self::set_internal_resistor_lp)
+    #[inline(always)]
+    pub fn internal_resistor_lp(mut self, resistor: Pull) -> Self {
+        self.set_internal_resistor_lp(resistor);
+        self
+    }
+
+    /// Reads the currently configured pull back from PUPDR
+    pub fn pull(&self) -> Pull {
+        let bits = unsafe {
+            let r = (*Gpio::<P>::ptr()).pupdr.read();
+
+            n_reg_r!(N, r, pupdr)
+        };
+
+        // 0b11 is reserved and never written by the HAL
+        bits.try_into().unwrap()
+    }
+
+    /// Reads the pull applied in Standby / Shutdown mode back from PWR PUCRx / PDCRx
+    ///
+    /// See [`set_internal_resistor_lp`](Self::set_internal_resistor_lp)
+    pub fn standby_pull(&self) -> Pull {
+        let pwr = unsafe { &*crate::pac::PWR::PTR };
+
+        macro_rules! outer {
+            ($p:ident) => {{
+                macro_rules! inner {
+                    ($n:literal) => {{
+                        paste! {
+                            // pucra has missing fields
+                            let pur = unsafe {
+                                &*(&pwr.[<pucr $p>] as *const _ as *const stm32wb::Reg<crate::pac::pwr::pucrb::PUCRB_SPEC>)
+                            };
+                            // pucra, pucrb have missing fields
+                            let pdr = unsafe {
+                                &*(&pwr.[<pdcr $p>] as *const _ as *const stm32wb::Reg<crate::pac::pwr::pdcrc::PDCRC_SPEC>)
+                            };
+
+                            (pur.read().[<pu $n>]().bit(), pdr.read().[<pd $n>]().bit())
+                        }
+                    }};
+                }
+
+                n!(N, inner)
+            }};
+        }
+
+        match p!(P, outer) {
+            (true, _) => Pull::Up,
+            (false, true) => Pull::Down,
+            (false, false) => Pull::Floating,
+        }
+    }
 }
 
 impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
@@ -308,20 +423,25 @@ impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
         }
     }
 
-    fn _is_set_low(&self) -> bool {
+    /// Inverts the output with a single ODR read and a single BSRR write
+    fn _toggle(&mut self) {
         unsafe {
-            let r = (*Gpio::<P>::ptr()).odr.read();
+            let gpio = &*Gpio::<P>::ptr();
+            let odr = gpio.odr.read().bits();
+            let mask = 1 << N;
 
-            n_reg_r_bit!(N, r, odr)
+            // Reset if set, set if reset
+            gpio.bsrr
+                .write(|w| w.bits(((odr & mask) << 16) | (!odr & mask)));
         }
     }
 
-    fn _is_low(&self) -> bool {
-        unsafe {
-            let r = (*Gpio::<P>::ptr()).idr.read();
+    fn _is_set_low(&self) -> bool {
+        output_is_set_low(unsafe { &*Gpio::<P>::ptr() }, N)
+    }
 
-            n_reg_r_bit!(N, r, idr)
-        }
+    fn _is_low(&self) -> bool {
+        input_is_low(unsafe { &*Gpio::<P>::ptr() }, N)
     }
 }
 
@@ -363,13 +483,49 @@ impl<const P: char, const N: u8, MODE> Pin<P, N, Output<MODE>> {
         self._is_set_low()
     }
 
+    /// Inverts the output level
+    ///
+    /// ODR is read once and the new level is written to BSRR in a single store. This isn't
+    /// atomic against the other CPU writing the same port, use a hardware semaphore (HSEM) for
+    /// pins shared between the cores.
     #[inline(always)]
     pub fn toggle(&mut self) {
-        if self.is_set_high() {
-            self.set_low();
-        } else {
-            self.set_high();
-        }
+        self._toggle();
+    }
+}
+
+/// Bidirectional open-drain pin
+///
+/// The pin either pulls the line low or releases it, leaving the level to the pull-up. Reading
+/// with [`is_high`](Self::is_high) / [`is_low`](Self::is_low) samples the pad, so a device on the
+/// bus holding the line low is observed, as needed for one-wire or bit-banged I2C:
+///
+/// ```ignore
+/// pin.drive_low();
+/// delay.delay_us(480);
+/// pin.release();
+/// delay.delay_us(70);
+/// let present = pin.is_low();
+/// ```
+impl<const P: char, const N: u8> Pin<P, N, Output<OpenDrain>> {
+    /// Pulls the line low
+    #[inline(always)]
+    pub fn drive_low(&mut self) {
+        self._set_low();
+    }
+
+    /// Releases the line (high impedance), same as [`set_high`](Self::set_high)
+    #[inline(always)]
+    pub fn release(&mut self) {
+        self._set_high();
+    }
+
+    /// The pin is currently releasing the line
+    ///
+    /// This is the output state, use [`is_high`](Self::is_high) to read the actual line level
+    #[inline(always)]
+    pub fn is_released(&self) -> bool {
+        !self._is_set_low()
     }
 }
 
@@ -386,11 +542,55 @@ where
     pub fn is_low(&self) -> bool {
         self._is_low()
     }
+
+    /// Input level of the pin (IDR)
+    #[inline(always)]
+    pub fn state(&self) -> PinState {
+        if self._is_low() {
+            PinState::Low
+        } else {
+            PinState::High
+        }
+    }
+}
+
+/// Pin used by the debugger (SWD / JTAG) after reset
+///
+/// [`GpioExt::split`] hands out PA13 (SWDIO), PA14 (SWCLK), PA15 (JTDI), PB3 (JTDO / SWO) and
+/// PB4 (NJTRST) wrapped in this type.
+///
+/// The pin has to be released explicitly before it can be reconfigured, changing its mode
+/// disconnects the debugger.
+pub struct Debugger<PIN>(PIN);
+
+impl<PIN> Debugger<PIN> {
+    /// Releases the pin for general use
+    pub fn release(self) -> PIN {
+        self.0
+    }
+}
+
+macro_rules! parts_field {
+    ($ty:ty) => {
+        $ty
+    };
+    ($ty:ty, $wrapper:ident) => {
+        super::$wrapper<$ty>
+    };
+}
+
+macro_rules! parts_field_new {
+    ($pin:expr) => {
+        $pin
+    };
+    ($pin:expr, $wrapper:ident) => {
+        super::$wrapper($pin)
+    };
 }
 
 macro_rules! gpio {
     ($GPIOX:ident, $port_id:expr, [
-        $($PXi:ident: ($i:expr, [$($A:literal),*] $(, $MODE:ty)?),)*
+        $($PXi:ident: ($i:expr, [$($A:literal),*] $(, $MODE:ty)?) $(=> $wrapper:ident)?,)*
     ]) => {
         paste! {
             pub mod [<$GPIOX:lower>] {
@@ -399,7 +599,7 @@ macro_rules! gpio {
 
                 pub struct Parts {
                     $(
-                        pub [<$PXi:lower>]: $PXi $(<$MODE>)?,
+                        pub [<$PXi:lower>]: parts_field!($PXi $(<$MODE>)? $(, $wrapper)?),
                     )*
                 }
 
@@ -408,12 +608,18 @@ macro_rules! gpio {
                     type REC = rec::$GPIOX;
 
                     fn split(self, rec: &mut Self::REC) -> Parts {
-                        rec.enable();
+                        let parts = self.split_without_reset(rec);
                         rec.reset();
 
+                        parts
+                    }
+
+                    fn split_without_reset(self, rec: &mut Self::REC) -> Parts {
+                        rec.enable();
+
                         Parts {
                             $(
-                                [<$PXi:lower>]: $PXi::new(),
+                                [<$PXi:lower>]: parts_field_new!($PXi::new() $(, $wrapper)?),
                             )*
                         }
                     }
@@ -421,6 +627,10 @@ macro_rules! gpio {
 
                 $(
                     pub type $PXi<MODE = super::Input> = super::Pin<$port_id, $i, MODE>;
+
+                    $(
+                        impl<MODE> super::marker::IntoAf<$A> for $PXi<MODE> {}
+                    )*
                 )*
             }
 
@@ -435,25 +645,55 @@ struct Gpio<const P: char>;
 
 impl<const P: char> Gpio<P> {
     const fn ptr() -> *const crate::pac::gpioa::RegisterBlock {
-        match P {
-            'A' => crate::pac::GPIOA::PTR,
-            'B' => crate::pac::GPIOB::PTR as _,
-            'C' => crate::pac::GPIOC::PTR as _,
-            'D' => crate::pac::GPIOD::PTR as _,
-            'E' => crate::pac::GPIOE::PTR as _,
-            'H' => crate::pac::GPIOH::PTR as _,
-            _ => unreachable!(),
-        }
+        port_ptr(P)
+    }
+}
+
+/// Register block of port `p`
+const fn port_ptr(p: char) -> *const crate::pac::gpioa::RegisterBlock {
+    match p {
+        'A' => crate::pac::GPIOA::PTR,
+        'B' => crate::pac::GPIOB::PTR as _,
+        'C' => crate::pac::GPIOC::PTR as _,
+        'D' => crate::pac::GPIOD::PTR as _,
+        'E' => crate::pac::GPIOE::PTR as _,
+        'H' => crate::pac::GPIOH::PTR as _,
+        _ => unreachable!(),
     }
 }
 
+/// Data registers read by the level getters of [`Pin`] and [`ErasedPin`]
+trait DataRegisters {
+    fn idr(&self) -> u32;
+    fn odr(&self) -> u32;
+}
+
+impl DataRegisters for crate::pac::gpioa::RegisterBlock {
+    fn idr(&self) -> u32 {
+        self.idr.read().bits()
+    }
+
+    fn odr(&self) -> u32 {
+        self.odr.read().bits()
+    }
+}
+
+/// The level on pin `n` is low (IDR)
+///
+/// For an open-drain output, this is the level on the line, not the driven level.
+fn input_is_low(regs: &impl DataRegisters, n: u8) -> bool {
+    regs.idr() & (1 << n) == 0
+}
+
+/// Pin `n` is driven low (ODR)
+fn output_is_set_low(regs: &impl DataRegisters, n: u8) -> bool {
+    regs.odr() & (1 << n) == 0
+}
+
+#[cfg(feature = "eh1")]
 mod hal {
-    use super::convert::PinMode;
     use super::*;
-    use embedded_hal::digital::blocking::{
-        InputPin, IoPin, OutputPin, StatefulOutputPin, ToggleableOutputPin,
-    };
-    use embedded_hal::digital::ErrorType;
+    use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
 
     impl<const P: char, const N: u8, MODE> ErrorType for Pin<P, N, MODE> {
         type Error = Infallible;
@@ -475,84 +715,36 @@ mod hal {
 
     impl<const P: char, const N: u8, OType> StatefulOutputPin for Pin<P, N, Output<OType>> {
         #[inline(always)]
-        fn is_set_low(&self) -> Result<bool, Self::Error> {
-            Ok(self.is_set_low())
+        fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(Pin::is_set_low(self))
         }
 
         #[inline(always)]
-        fn is_set_high(&self) -> Result<bool, Self::Error> {
-            Ok(self.is_set_high())
+        fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(Pin::is_set_high(self))
         }
-    }
 
-    impl<const P: char, const N: u8, OType> ToggleableOutputPin for Pin<P, N, Output<OType>> {
         #[inline(always)]
         fn toggle(&mut self) -> Result<(), Self::Error> {
-            self.toggle();
+            Pin::toggle(self);
             Ok(())
         }
     }
 
+    /// Reads the pad level (IDR). For an `Output<OpenDrain>` pin, this is the level on the line,
+    /// which differs from the driven level (ODR) while another device pulls it low.
     impl<const P: char, const N: u8, MODE> InputPin for Pin<P, N, MODE>
     where
         MODE: marker::Readable,
     {
         #[inline(always)]
-        fn is_low(&self) -> Result<bool, Self::Error> {
-            Ok(self.is_low())
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self._is_low())
         }
 
         #[inline(always)]
-        fn is_high(&self) -> Result<bool, Self::Error> {
-            Ok(self.is_high())
-        }
-    }
-
-    impl<const P: char, const N: u8> IoPin<Self, Self> for Pin<P, N, Output<OpenDrain>> {
-        type Error = Infallible;
-
-        fn into_input_pin(self) -> Result<Self, Self::Error> {
-            Ok(self)
-        }
-
-        fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
-            self.set_state(state);
-            Ok(self)
-        }
-    }
-
-    impl<const P: char, const N: u8, OType> IoPin<Pin<P, N, Input>, Self> for Pin<P, N, Output<OType>>
-    where
-        Output<OType>: PinMode,
-    {
-        type Error = Infallible;
-
-        fn into_input_pin(self) -> Result<Pin<P, N, Input>, Self::Error> {
-            Ok(self.into_input())
-        }
-
-        fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
-            self.set_state(state);
-            Ok(self)
-        }
-    }
-
-    impl<const P: char, const N: u8, OType> IoPin<Self, Pin<P, N, Output<OType>>> for Pin<P, N, Input>
-    where
-        Output<OType>: PinMode,
-    {
-        type Error = Infallible;
-
-        fn into_input_pin(self) -> Result<Self, Self::Error> {
-            Ok(self)
-        }
-
-        fn into_output_pin(
-            mut self,
-            state: PinState,
-        ) -> Result<Pin<P, N, Output<OType>>, Self::Error> {
-            self._set_state(state);
-            Ok(self.into_mode())
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(self._is_low())
         }
     }
 }
@@ -572,9 +764,9 @@ gpio! {
         PA10: (10, [1, 3, 4, 7, 10, 11, 13, 14, 15]),
         PA11: (11, [1, 2, 5, 7, 10, 12, 15]),
         PA12: (12, [1, 5, 7, 8, 10, 15]),
-        PA13: (13, [0, 8, 10, 13, 15]),
-        PA14: (14, [0, 1, 4, 11, 13, 15]),
-        PA15: (15, [0, 1, 2, 5, 6, 11, 15]),
+        PA13: (13, [0, 8, 10, 13, 15]) => Debugger,
+        PA14: (14, [0, 1, 4, 11, 13, 15]) => Debugger,
+        PA15: (15, [0, 1, 2, 5, 6, 11, 15]) => Debugger,
     ]
 }
 
@@ -583,8 +775,8 @@ gpio! {
         PB0: (0, [6, 12, 15]),
         PB1: (1, [8, 14, 15]),
         PB2: (2, [0, 1, 4, 5, 11, 13, 15]),
-        PB3: (3, [0, 1, 5, 7, 11, 13, 15]),
-        PB4: (4, [0, 4, 5, 7, 9, 11, 13, 14, 15]),
+        PB3: (3, [0, 1, 5, 7, 11, 13, 15]) => Debugger,
+        PB4: (4, [0, 4, 5, 7, 9, 11, 13, 14, 15]) => Debugger,
         PB5: (5, [1, 4, 5, 7, 8, 9, 11, 12, 13, 14, 15]),
         PB6: (6, [0, 1, 4, 7, 9, 11, 13, 14, 15]),
         PB7: (7, [1, 3, 4, 7, 9, 11, 14, 15]),
@@ -658,3 +850,83 @@ gpio! {
         PH3: (3, [0, 15]),
     ]
 }
+
+#[cfg(all(test, not(test)))]
+mod test {
+    use super::{input_is_low, output_is_set_low, DataRegisters, Input, Speed, PA5};
+
+    #[test]
+    /// Test that `Speed` round-trips through the 2-bit OSPEEDR encoding
+    fn speed_round_trip() {
+        let speeds = [Speed::Low, Speed::Medium, Speed::Fast, Speed::High];
+
+        for (bits, speed) in speeds.into_iter().enumerate() {
+            assert_eq!(u8::from(speed), bits as u8);
+            assert_eq!(Speed::try_from(bits as u8).unwrap(), speed);
+        }
+
+        assert!(Speed::try_from(0b100).is_err());
+    }
+
+    #[test]
+    /// Test that `pin_exists` matches the pins generated by `gpio!`
+    fn pin_exists() {
+        use super::erased::pin_exists;
+
+        assert!(pin_exists(PA5::<Input>::PORT, PA5::<Input>::NUMBER));
+        assert!(pin_exists('D', 15));
+        assert!(pin_exists('E', 4));
+        assert!(pin_exists('H', 3));
+
+        assert!(!pin_exists('A', 16));
+        assert!(!pin_exists('E', 5));
+        assert!(!pin_exists('H', 2));
+        assert!(!pin_exists('F', 0));
+    }
+
+    struct MockRegisters {
+        idr: u32,
+        odr: u32,
+    }
+
+    impl DataRegisters for MockRegisters {
+        fn idr(&self) -> u32 {
+            self.idr
+        }
+
+        fn odr(&self) -> u32 {
+            self.odr
+        }
+    }
+
+    #[test]
+    /// Test that the input level is read from IDR and the output level from ODR, as an
+    /// open-drain output pulled low by another device reads low while it's released
+    fn open_drain_reads_idr() {
+        // Released (ODR set), another device pulls the line low
+        let regs = MockRegisters {
+            idr: !(1 << 5),
+            odr: 1 << 5,
+        };
+        assert!(input_is_low(&regs, 5));
+        assert!(!output_is_set_low(&regs, 5));
+
+        // Released, the line is high
+        let regs = MockRegisters {
+            idr: 1 << 5,
+            odr: 1 << 5,
+        };
+        assert!(!input_is_low(&regs, 5));
+        assert!(!output_is_set_low(&regs, 5));
+
+        // Driven low, the other pins are high
+        let regs = MockRegisters {
+            idr: !(1 << 5),
+            odr: !(1 << 5),
+        };
+        assert!(input_is_low(&regs, 5));
+        assert!(output_is_set_low(&regs, 5));
+        assert!(!input_is_low(&regs, 4));
+        assert!(!output_is_set_low(&regs, 6));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::i2c;
 use sealed::sealed;
 
+/// Pin `PIN` of peripheral `PER` in alternate function `A`
 #[sealed]
 pub trait PinA<PIN, PER> {
     const A: u8;
@@ -11,7 +12,7 @@ macro_rules! pin {
         $(
             $(
                 #[sealed]
-                impl PinA<$PIN, crate::pac::$PER> for crate::gpio::$PX<crate::gpio::Alternate<$A>> {
+                impl<OType> PinA<$PIN, crate::pac::$PER> for crate::gpio::$PX<crate::gpio::Alternate<$A, OType>> {
                     const A: u8 = $A;
                 }
             )*
@@ -85,8 +85,12 @@ impl<const P: char, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
         self.into_mode().internal_resistor(Pull::Down)
     }
 
+    /// Configures the pin to operate as an open drain output pin
+    ///
+    /// Initial state will be high (line released)
     #[inline(always)]
-    pub fn into_open_drain_output(self) -> Pin<P, N, Output<OpenDrain>> {
+    pub fn into_open_drain_output(mut self) -> Pin<P, N, Output<OpenDrain>> {
+        self._set_high();
         self.into_mode()
     }
 
@@ -124,6 +128,35 @@ impl<const P: char, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
         self.into_mode()
     }
 
+    /// Like [`into_alternate`](Self::into_alternate), then sets the output speed
+    ///
+    /// OSPEEDR isn't touched by the mode conversion. As a guideline, [`Speed::Low`] is enough
+    /// for UART and I2C, while SPI, QUADSPI and SAI need [`Speed::Fast`] or [`Speed::High`] at
+    /// clocks above a few MHz, see [`Speed`].
+    #[inline(always)]
+    pub fn into_alternate_with_speed<const A: u8>(
+        self,
+        speed: Speed,
+    ) -> Pin<P, N, Alternate<A, PushPull>>
+    where
+        Self: super::marker::IntoAf<A>,
+    {
+        self.into_alternate().with_speed(speed)
+    }
+
+    /// Like [`into_alternate_open_drain`](Self::into_alternate_open_drain), then sets the output
+    /// speed, see [`into_alternate_with_speed`](Self::into_alternate_with_speed)
+    #[inline(always)]
+    pub fn into_alternate_open_drain_with_speed<const A: u8>(
+        self,
+        speed: Speed,
+    ) -> Pin<P, N, Alternate<A, OpenDrain>>
+    where
+        Self: super::marker::IntoAf<A>,
+    {
+        self.into_alternate_open_drain().with_speed(speed)
+    }
+
     #[inline(always)]
     pub fn into_mode<M: PinMode>(mut self) -> Pin<P, N, M>
     where
@@ -134,82 +167,192 @@ impl<const P: char, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
     }
 
     fn _set_mode<M: PinMode>(&mut self) {
-        unsafe {
-            if MODE::OTYPER != M::OTYPER {
-                if let Some(otyper) = M::OTYPER {
-                    (*Gpio::<P>::ptr()).otyper.modify(|_, w| match N {
-                        0 => w.ot0().bit(otyper),
-                        1 => w.ot1().bit(otyper),
-                        2 => w.ot2().bit(otyper),
-                        3 => w.ot3().bit(otyper),
-                        4 => w.ot4().bit(otyper),
-                        5 => w.ot5().bit(otyper),
-                        6 => w.ot6().bit(otyper),
-                        7 => w.ot7().bit(otyper),
-                        8 => w.ot8().bit(otyper),
-                        9 => w.ot9().bit(otyper),
-                        10 => w.ot10().bit(otyper),
-                        11 => w.ot11().bit(otyper),
-                        12 => w.ot12().bit(otyper),
-                        13 => w.ot13().bit(otyper),
-                        14 => w.ot14().bit(otyper),
-                        15 => w.ot15().bit(otyper),
-                        _ => unreachable!(),
-                    });
-                }
-            }
-
-            if MODE::AFR != M::AFR {
-                if let Some(afr) = M::AFR {
-                    if N < 8 {
-                        (*Gpio::<P>::ptr()).afrl.modify(|_, w| match N {
-                            0 => w.afsel0().variant(afr),
-                            1 => w.afsel1().variant(afr),
-                            2 => w.afsel2().variant(afr),
-                            3 => w.afsel3().variant(afr),
-                            4 => w.afsel4().variant(afr),
-                            5 => w.afsel5().variant(afr),
-                            6 => w.afsel6().variant(afr),
-                            7 => w.afsel7().variant(afr),
-                            _ => unreachable!(),
-                        });
-                    } else {
-                        (*Gpio::<P>::ptr()).afrh.modify(|_, w| match N {
-                            8 => w.afsel8().variant(afr),
-                            9 => w.afsel9().variant(afr),
-                            10 => w.afsel10().variant(afr),
-                            11 => w.afsel11().variant(afr),
-                            12 => w.afsel12().variant(afr),
-                            13 => w.afsel13().variant(afr),
-                            14 => w.afsel14().variant(afr),
-                            15 => w.afsel15().variant(afr),
-                            _ => unreachable!(),
-                        });
-                    }
-                }
-            }
-
-            if MODE::MODER != M::MODER {
-                (*Gpio::<P>::ptr()).moder.modify(|_, w| match N {
-                    0 => w.moder0().variant(M::MODER),
-                    1 => w.moder1().variant(M::MODER),
-                    2 => w.moder2().variant(M::MODER),
-                    3 => w.moder3().variant(M::MODER),
-                    4 => w.moder4().variant(M::MODER),
-                    5 => w.moder5().variant(M::MODER),
-                    6 => w.moder6().variant(M::MODER),
-                    7 => w.moder7().variant(M::MODER),
-                    8 => w.moder8().variant(M::MODER),
-                    9 => w.moder9().variant(M::MODER),
-                    10 => w.moder10().variant(M::MODER),
-                    11 => w.moder11().variant(M::MODER),
-                    12 => w.moder12().variant(M::MODER),
-                    13 => w.moder13().variant(M::MODER),
-                    14 => w.moder14().variant(M::MODER),
-                    15 => w.moder15().variant(M::MODER),
-                    _ => unreachable!(),
-                });
-            }
+        // SAFETY: Only the bits of pin N are modified
+        let regs = unsafe { &*Gpio::<P>::ptr() };
+
+        set_mode::<MODE, M>(regs, N);
+    }
+}
+
+/// Mode related GPIO registers, abstracted for testability
+trait ModeRegisters {
+    fn set_otyper(&self, n: u8, otyper: bool);
+    fn set_afr(&self, n: u8, afr: u8);
+    fn set_moder(&self, n: u8, moder: u8);
+}
+
+impl ModeRegisters for crate::pac::gpioa::RegisterBlock {
+    fn set_otyper(&self, n: u8, otyper: bool) {
+        self.otyper.modify(|_, w| match n {
+            0 => w.ot0().bit(otyper),
+            1 => w.ot1().bit(otyper),
+            2 => w.ot2().bit(otyper),
+            3 => w.ot3().bit(otyper),
+            4 => w.ot4().bit(otyper),
+            5 => w.ot5().bit(otyper),
+            6 => w.ot6().bit(otyper),
+            7 => w.ot7().bit(otyper),
+            8 => w.ot8().bit(otyper),
+            9 => w.ot9().bit(otyper),
+            10 => w.ot10().bit(otyper),
+            11 => w.ot11().bit(otyper),
+            12 => w.ot12().bit(otyper),
+            13 => w.ot13().bit(otyper),
+            14 => w.ot14().bit(otyper),
+            15 => w.ot15().bit(otyper),
+            _ => unreachable!(),
+        });
+    }
+
+    fn set_afr(&self, n: u8, afr: u8) {
+        if n < 8 {
+            self.afrl.modify(|_, w| match n {
+                0 => w.afsel0().variant(afr),
+                1 => w.afsel1().variant(afr),
+                2 => w.afsel2().variant(afr),
+                3 => w.afsel3().variant(afr),
+                4 => w.afsel4().variant(afr),
+                5 => w.afsel5().variant(afr),
+                6 => w.afsel6().variant(afr),
+                7 => w.afsel7().variant(afr),
+                _ => unreachable!(),
+            });
+        } else {
+            self.afrh.modify(|_, w| match n {
+                8 => w.afsel8().variant(afr),
+                9 => w.afsel9().variant(afr),
+                10 => w.afsel10().variant(afr),
+                11 => w.afsel11().variant(afr),
+                12 => w.afsel12().variant(afr),
+                13 => w.afsel13().variant(afr),
+                14 => w.afsel14().variant(afr),
+                15 => w.afsel15().variant(afr),
+                _ => unreachable!(),
+            });
+        }
+    }
+
+    fn set_moder(&self, n: u8, moder: u8) {
+        self.moder.modify(|_, w| match n {
+            0 => w.moder0().variant(moder),
+            1 => w.moder1().variant(moder),
+            2 => w.moder2().variant(moder),
+            3 => w.moder3().variant(moder),
+            4 => w.moder4().variant(moder),
+            5 => w.moder5().variant(moder),
+            6 => w.moder6().variant(moder),
+            7 => w.moder7().variant(moder),
+            8 => w.moder8().variant(moder),
+            9 => w.moder9().variant(moder),
+            10 => w.moder10().variant(moder),
+            11 => w.moder11().variant(moder),
+            12 => w.moder12().variant(moder),
+            13 => w.moder13().variant(moder),
+            14 => w.moder14().variant(moder),
+            15 => w.moder15().variant(moder),
+            _ => unreachable!(),
+        });
+    }
+}
+
+/// Switches pin `n` from mode `FROM` to mode `TO`
+///
+/// The output type and the alternate function are configured before MODER enables
+/// the new driver, so the pin never drives the line with a stale configuration.
+fn set_mode<FROM: PinMode, TO: PinMode>(regs: &impl ModeRegisters, n: u8) {
+    if FROM::OTYPER != TO::OTYPER {
+        if let Some(otyper) = TO::OTYPER {
+            regs.set_otyper(n, otyper);
+        }
+    }
+
+    if FROM::AFR != TO::AFR {
+        if let Some(afr) = TO::AFR {
+            regs.set_afr(n, afr);
         }
     }
+
+    if FROM::MODER != TO::MODER {
+        regs.set_moder(n, TO::MODER);
+    }
+}
+
+/// Configures pin `n` to mode `TO`, without knowing its current mode
+///
+/// All registers of `TO` are written, in the same order as [`set_mode`].
+pub(super) fn force_mode<TO: PinMode>(regs: &crate::pac::gpioa::RegisterBlock, n: u8) {
+    if let Some(otyper) = TO::OTYPER {
+        regs.set_otyper(n, otyper);
+    }
+
+    if let Some(afr) = TO::AFR {
+        regs.set_afr(n, afr);
+    }
+
+    regs.set_moder(n, TO::MODER);
+}
+
+#[cfg(all(test, not(test)))]
+mod test {
+    use super::*;
+    use core::cell::RefCell;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Write {
+        Otyper(bool),
+        Afr(u8),
+        Moder(u8),
+    }
+
+    #[derive(Default)]
+    struct MockRegisters {
+        writes: RefCell<heapless::Vec<Write, 8>>,
+    }
+
+    impl ModeRegisters for MockRegisters {
+        fn set_otyper(&self, _: u8, otyper: bool) {
+            self.writes.borrow_mut().push(Write::Otyper(otyper)).unwrap();
+        }
+
+        fn set_afr(&self, _: u8, afr: u8) {
+            self.writes.borrow_mut().push(Write::Afr(afr)).unwrap();
+        }
+
+        fn set_moder(&self, _: u8, moder: u8) {
+            self.writes.borrow_mut().push(Write::Moder(moder)).unwrap();
+        }
+    }
+
+    #[test]
+    /// Test that the output type is configured before the output driver is enabled
+    fn input_to_open_drain() {
+        let regs = MockRegisters::default();
+        set_mode::<Input, Output<OpenDrain>>(&regs, 3);
+
+        assert_eq!(
+            regs.writes.borrow().as_slice(),
+            &[Write::Otyper(true), Write::Moder(0b01)]
+        );
+    }
+
+    #[test]
+    /// Test that switching between output types doesn't touch MODER
+    fn push_pull_to_open_drain() {
+        let regs = MockRegisters::default();
+        set_mode::<Output<PushPull>, Output<OpenDrain>>(&regs, 3);
+
+        assert_eq!(regs.writes.borrow().as_slice(), &[Write::Otyper(true)]);
+    }
+
+    #[test]
+    /// Test that output type and alternate function are set before MODER
+    fn input_to_alternate_open_drain() {
+        let regs = MockRegisters::default();
+        set_mode::<Input, Alternate<4, OpenDrain>>(&regs, 9);
+
+        assert_eq!(
+            regs.writes.borrow().as_slice(),
+            &[Write::Otyper(true), Write::Afr(4), Write::Moder(0b10)]
+        );
+    }
 }
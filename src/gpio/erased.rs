@@ -0,0 +1,232 @@
+//! Pins with the port and the pin number known at runtime only
+
+use super::convert::{force_mode, PinMode};
+use super::*;
+use crate::pac::gpioa;
+
+/// Pin with its port and number erased from the type
+///
+/// Created from a typed pin with [`Pin::erase`], or resolved from a `(port, number)` pair with
+/// [`ErasedPin::steal`], e.g. for a pin map read from flash:
+///
+/// ```ignore
+/// let led: ErasedPin<Output<PushPull>> = unsafe { ErasedPin::steal('B', 5)? };
+/// ```
+pub struct ErasedPin<MODE> {
+    port: char,
+    n: u8,
+    _mode: PhantomData<MODE>,
+}
+
+/// The `(port, number)` pair doesn't name a pin of the STM32WB55, see [`pin_exists`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidPin;
+
+/// Whether pin `n` of port `p` exists on the STM32WB55
+///
+/// Ports A to D have 16 pins, port E has PE0 - PE4 and port H has PH0, PH1 and PH3.
+pub const fn pin_exists(p: char, n: u8) -> bool {
+    match p {
+        'A' | 'B' | 'C' | 'D' => n < 16,
+        'E' => n <= 4,
+        'H' => matches!(n, 0 | 1 | 3),
+        _ => false,
+    }
+}
+
+impl<MODE> ErasedPin<MODE> {
+    /// Port and number of the pin, e.g. `('A', 5)` for PA5
+    pub fn id(&self) -> (char, u8) {
+        (self.port, self.n)
+    }
+
+    fn regs(&self) -> &'static gpioa::RegisterBlock {
+        unsafe { &*port_ptr(self.port) }
+    }
+
+    fn _is_set_low(&self) -> bool {
+        output_is_set_low(self.regs(), self.n)
+    }
+
+    fn _is_low(&self) -> bool {
+        input_is_low(self.regs(), self.n)
+    }
+}
+
+impl<MODE> PinExt for ErasedPin<MODE> {
+    type Mode = MODE;
+
+    #[inline(always)]
+    fn pin_id(&self) -> u8 {
+        self.n
+    }
+
+    #[inline(always)]
+    fn port_id(&self) -> u8 {
+        self.port as u8 - b'A'
+    }
+}
+
+impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
+    /// Erases the port and the pin number from the type
+    #[inline(always)]
+    pub fn erase(self) -> ErasedPin<MODE> {
+        ErasedPin {
+            port: P,
+            n: N,
+            _mode: PhantomData,
+        }
+    }
+}
+
+impl<const P: char, const N: u8, MODE> From<Pin<P, N, MODE>> for ErasedPin<MODE> {
+    #[inline(always)]
+    fn from(pin: Pin<P, N, MODE>) -> Self {
+        pin.erase()
+    }
+}
+
+impl<MODE> ErasedPin<MODE>
+where
+    MODE: PinMode + marker::NotAlt,
+{
+    /// Resolves the pin at runtime and configures it to `MODE`
+    ///
+    /// Fails with [`InvalidPin`] if the pin doesn't exist. Alternate functions are excluded, as
+    /// they can't be checked against the pin at compile time.
+    ///
+    /// # Safety
+    ///
+    /// This bypasses [`GpioExt::split`], like `Peripherals::steal`. The port must be enabled in
+    /// RCC, and the pin must not be in use through another handle, e.g. one from `split`.
+    pub unsafe fn steal(port: char, n: u8) -> Result<Self, InvalidPin> {
+        if !pin_exists(port, n) {
+            return Err(InvalidPin);
+        }
+
+        let pin = Self {
+            port,
+            n,
+            _mode: PhantomData,
+        };
+        force_mode::<MODE>(pin.regs(), n);
+
+        Ok(pin)
+    }
+}
+
+impl<MODE> ErasedPin<Output<MODE>> {
+    #[inline(always)]
+    pub fn set_high(&mut self) {
+        self.regs().bsrr.write(|w| unsafe { w.bits(1 << self.n) });
+    }
+
+    #[inline(always)]
+    pub fn set_low(&mut self) {
+        self.regs()
+            .bsrr
+            .write(|w| unsafe { w.bits(1 << (self.n + 16)) });
+    }
+
+    #[inline(always)]
+    pub fn set_state(&mut self, state: PinState) {
+        match state {
+            PinState::Low => self.set_low(),
+            PinState::High => self.set_high(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_set_high(&self) -> bool {
+        !self.is_set_low()
+    }
+
+    #[inline(always)]
+    pub fn is_set_low(&self) -> bool {
+        self._is_set_low()
+    }
+
+    /// Inverts the output level, see [`Pin::toggle`]
+    #[inline(always)]
+    pub fn toggle(&mut self) {
+        let regs = self.regs();
+        let odr = regs.odr.read().bits();
+        let mask = 1 << self.n;
+
+        // Reset if set, set if reset
+        regs.bsrr
+            .write(|w| unsafe { w.bits(((odr & mask) << 16) | (!odr & mask)) });
+    }
+}
+
+impl<MODE> ErasedPin<MODE>
+where
+    MODE: marker::Readable,
+{
+    #[inline(always)]
+    pub fn is_high(&self) -> bool {
+        !self.is_low()
+    }
+
+    #[inline(always)]
+    pub fn is_low(&self) -> bool {
+        self._is_low()
+    }
+}
+
+#[cfg(feature = "eh1")]
+mod hal {
+    use super::*;
+    use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
+
+    impl<MODE> ErrorType for ErasedPin<MODE> {
+        type Error = Infallible;
+    }
+
+    impl<OType> OutputPin for ErasedPin<Output<OType>> {
+        #[inline(always)]
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.set_low();
+            Ok(())
+        }
+
+        #[inline(always)]
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set_high();
+            Ok(())
+        }
+    }
+
+    impl<OType> StatefulOutputPin for ErasedPin<Output<OType>> {
+        #[inline(always)]
+        fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(ErasedPin::is_set_low(self))
+        }
+
+        #[inline(always)]
+        fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(ErasedPin::is_set_high(self))
+        }
+
+        #[inline(always)]
+        fn toggle(&mut self) -> Result<(), Self::Error> {
+            ErasedPin::toggle(self);
+            Ok(())
+        }
+    }
+
+    impl<MODE> InputPin for ErasedPin<MODE>
+    where
+        MODE: marker::Readable,
+    {
+        #[inline(always)]
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(ErasedPin::is_low(self))
+        }
+
+        #[inline(always)]
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(ErasedPin::is_high(self))
+        }
+    }
+}
@@ -1,10 +1,12 @@
+use crate::dma::{Channel, DmaRequest, Transfer};
+use crate::gpio::alt::PinA;
+use crate::gpio::{Alternate, OpenDrain, Pin};
 use crate::pac::{I2C1, I2C3};
 use crate::rcc::{rec, Clocks, TrustedClocks};
 use crate::time::Hertz;
 use core::cmp::max;
 use core::marker::PhantomData;
-use embedded_hal::i2c::blocking::Operation;
-use embedded_hal::i2c::{NoAcknowledgeSource, SevenBitAddress, TenBitAddress};
+use embedded_hal::i2c::{NoAcknowledgeSource, Operation, SevenBitAddress, TenBitAddress};
 use fugit::RateExtU32;
 use paste::paste;
 use sealed::sealed;
@@ -29,22 +31,57 @@ where
 {
 }
 
+/// SCL pin (marker)
+pub struct Scl;
+/// SDA pin (marker)
+pub struct Sda;
+/// SMBus alert pin (marker)
+pub struct Smba;
+
+#[sealed]
+impl<I2C, const P: char, const N: u8, const A: u8> SclPin<I2C> for Pin<P, N, Alternate<A, OpenDrain>> where
+    Self: PinA<Scl, I2C>
+{
+}
+
+#[sealed]
+impl<I2C, const P: char, const N: u8, const A: u8> SdaPin<I2C> for Pin<P, N, Alternate<A, OpenDrain>> where
+    Self: PinA<Sda, I2C>
+{
+}
+
+#[sealed]
+impl<I2C, const P: char, const N: u8, const A: u8> SmbaPin<I2C> for Pin<P, N, Alternate<A, OpenDrain>> where
+    Self: PinA<Smba, I2C>
+{
+}
+
 const SEVEN_BIT_ADDR_MODE: bool = false;
 const TEN_BIT_ADDR_MODE: bool = true;
 const RD_WRN_WRITE: bool = false;
 const RD_WRN_READ: bool = true;
 
+/// How a transfer segment begins
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Start {
+    /// START condition followed by the address, the bus must be idle
     Start,
+    /// Repeated START in the opposite direction, the previous segment must have ended with
+    /// [`Stop::Software`]
     Restart,
+    /// Continuation in the same direction without START, the previous segment must have ended
+    /// with [`Stop::Reload`]
     Reload,
 }
 
+/// How a transfer segment ends
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Stop {
+    /// The bus is held (TC), the next segment is a restart or a [`master_stop`](I2c::master_stop)
     Software,
+    /// The bus is held (TCR), the next segment continues in the same direction
     Reload,
+    /// STOP condition is sent after the last byte
     Automatic,
 }
 
@@ -53,6 +90,13 @@ pub enum Error {
     Bus,
     Arbitration,
     NoAcknowledge(NoAcknowledgeSource),
+    /// The requested segment doesn't fit the state of the ongoing transfer
+    InvalidState,
+    /// The DMA channel of a [`DmaTransfer`] reported a transfer error (TEIF)
+    Dma,
+    /// The length announced by the device exceeds the buffer, see
+    /// [`master_read_dynamic`](I2c::master_read_dynamic)
+    Overflow,
 }
 
 impl embedded_hal::i2c::Error for Error {
@@ -61,6 +105,9 @@ impl embedded_hal::i2c::Error for Error {
             Error::Bus => embedded_hal::i2c::ErrorKind::Bus,
             Error::Arbitration => embedded_hal::i2c::ErrorKind::ArbitrationLoss,
             Error::NoAcknowledge(nack) => embedded_hal::i2c::ErrorKind::NoAcknowledge(nack),
+            Error::InvalidState | Error::Dma | Error::Overflow => {
+                embedded_hal::i2c::ErrorKind::Other
+            }
         }
     }
 }
@@ -68,6 +115,75 @@ impl embedded_hal::i2c::Error for Error {
 #[derive(Debug)]
 pub enum ConfigError {
     I2cClockDisabled,
+    /// Maximum allowed frequency is 1 MHz
+    FrequencyTooHigh,
+    /// PCLK1 frequency must be at least 3/4 of SCL frequency
+    PclkTooLow,
+    /// The timing parameters can't be met with the I2C clock, the prescaler would overflow
+    InvalidTimingParams,
+    /// A communication is in progress on the bus
+    Busy,
+    /// The I2C kernel clock is below the minimum of the speed mode (2, 9 or 19 MHz)
+    KernelClockTooLow { min: Hertz, actual: Hertz },
+}
+
+/// Electrical characteristics of the bus used for the timing computation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingParams {
+    /// SCL / SDA rise time in ns
+    pub rise_ns: u32,
+    /// SCL / SDA fall time in ns
+    pub fall_ns: u32,
+}
+
+impl TimingParams {
+    /// Maximum rise and fall times of the speed mode of `frequency`, see RM0434 Rev 9 Table 192
+    pub fn for_frequency(frequency: Hertz) -> Self {
+        if frequency > 400.kHz::<1, 1>() {
+            Self {
+                rise_ns: 120,
+                fall_ns: 120,
+            }
+        } else if frequency > 100.kHz::<1, 1>() {
+            Self {
+                rise_ns: 300,
+                fall_ns: 300,
+            }
+        } else {
+            Self {
+                rise_ns: 1000,
+                fall_ns: 300,
+            }
+        }
+    }
+}
+
+/// Cleanup needed to leave the bus idle after a failed transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recovery {
+    /// Nothing left to do
+    Idle,
+    /// The master holds the bus after the last segment (TC), a STOP releases it
+    Stop,
+    /// The transfer stopped in the middle, reset the peripheral (PE) to release the lines
+    Reset,
+}
+
+impl Recovery {
+    const BUSY: u32 = 1 << 15;
+
+    /// Cleanup after `err`, given the raw `ISR` value at the time of the error
+    const fn after(err: Error, isr: u32) -> Self {
+        let flags = Flags::from_isr(isr);
+
+        match err {
+            // The master sends a STOP automatically after a NACK, see RM0434 Rev 9 32.4.9
+            Error::NoAcknowledge(_) => Self::Idle,
+            _ if isr & Self::BUSY == 0 => Self::Idle,
+            _ if flags.contains(Flags::TC) => Self::Stop,
+            _ => Self::Reset,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -79,6 +195,138 @@ pub enum Event {
     Stop,
     TransferComplete,
     Errors,
+    /// SMBus timeout (TIMEOUT)
+    ///
+    /// The SMBus events share their interrupt enable (ERRIE) with [`Event::Errors`], listening
+    /// to one of them enables all.
+    Timeout,
+    /// SMBus PEC error in reception (PECERR)
+    PecError,
+    /// SMBus alert on the SMBA pin (ALERT)
+    SmbAlert,
+}
+
+impl Event {
+    const ALL: [Event; 10] = [
+        Event::Transmit,
+        Event::Receive,
+        Event::AddressMatch,
+        Event::NotAcknowledge,
+        Event::Stop,
+        Event::TransferComplete,
+        Event::Errors,
+        Event::Timeout,
+        Event::PecError,
+        Event::SmbAlert,
+    ];
+
+    /// `ISR` flags raising the event
+    const fn isr_mask(self) -> u32 {
+        match self {
+            Event::Transmit => 1 << 1,
+            Event::Receive => 1 << 2,
+            Event::AddressMatch => 1 << 3,
+            Event::NotAcknowledge => 1 << 4,
+            Event::Stop => 1 << 5,
+            // TC | TCR
+            Event::TransferComplete => (1 << 6) | (1 << 7),
+            // BERR | ARLO | OVR
+            Event::Errors => (1 << 8) | (1 << 9) | (1 << 10),
+            Event::Timeout => 1 << 12,
+            Event::PecError => 1 << 11,
+            Event::SmbAlert => 1 << 13,
+        }
+    }
+
+    /// `CR1` interrupt enable bit of the event
+    const fn cr1_mask(self) -> u32 {
+        match self {
+            Event::Transmit => 1 << 1,
+            Event::Receive => 1 << 2,
+            Event::AddressMatch => 1 << 3,
+            Event::NotAcknowledge => 1 << 4,
+            Event::Stop => 1 << 5,
+            Event::TransferComplete => 1 << 6,
+            Event::Errors | Event::Timeout | Event::PecError | Event::SmbAlert => 1 << 7,
+        }
+    }
+
+    /// Events with a set flag in `isr` and an enabled interrupt in `cr1`
+    fn pending(isr: u32, cr1: u32) -> heapless::Vec<Event, 10> {
+        Self::ALL
+            .into_iter()
+            .filter(|event| isr & event.isr_mask() != 0 && cr1 & event.cr1_mask() != 0)
+            .collect()
+    }
+}
+
+/// Status flags of the I2C peripheral, a subset of the `ISR` register
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Flags(u32);
+
+impl Flags {
+    /// Transmit interrupt status, TXDR is empty and must be written
+    pub const TXIS: Self = Self(1 << 1);
+    /// Receive data register not empty
+    pub const RXNE: Self = Self(1 << 2);
+    /// Not acknowledge received
+    pub const NACKF: Self = Self(1 << 4);
+    /// Stop detection
+    pub const STOPF: Self = Self(1 << 5);
+    /// Transfer complete in master mode
+    pub const TC: Self = Self(1 << 6);
+    /// Transfer complete reload
+    pub const TCR: Self = Self(1 << 7);
+
+    const ALL: u32 = Self::TXIS.0
+        | Self::RXNE.0
+        | Self::NACKF.0
+        | Self::STOPF.0
+        | Self::TC.0
+        | Self::TCR.0;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Extracts the flags from a raw `ISR` value
+    pub const fn from_isr(isr: u32) -> Self {
+        Self(isr & Self::ALL)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for Flags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for Flags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl core::ops::BitAnd for Flags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
 }
 
 pub trait I2cExt: Sized {
@@ -90,7 +338,7 @@ pub trait I2cExt: Sized {
         clocks: impl Clocks + TrustedClocks<'a>,
         frequency: Hertz,
         rec: &mut Self::REC,
-    ) -> Result<I2c<'a, Self, PINS>, ConfigError>
+    ) -> Result<I2c<'a, Self, PINS>, (ConfigError, Self, PINS)>
     where
         PINS: Pins<Self>;
 }
@@ -104,8 +352,20 @@ pub struct I2c<'a, I2C, PINS> {
 pub type I2c1<'a, PINS> = I2c<'a, I2C1, PINS>;
 pub type I2c3<'a, PINS> = I2c<'a, I2C3, PINS>;
 
+/// Master read or write moved by DMA, see [`I2c::write_dma`] and [`I2c::read_dma`]
+///
+/// Keeps the I2C borrowed, so the transfer can be aborted on a bus error.
+pub struct DmaTransfer<'i, I2C, const D: u8, const C: u8, BUF> {
+    i2c: &'i mut I2C,
+    transfer: Transfer<Channel<D, C>, BUF>,
+}
+
 impl<I2C, PINS> I2c<'_, I2C, PINS> {
-    fn timings(i2cclk: Hertz, frequency: Hertz) -> [u8; 5] {
+    fn timings_with_params(
+        i2cclk: Hertz,
+        frequency: Hertz,
+        params: TimingParams,
+    ) -> Result<[u8; 5], ConfigError> {
         let ratio = (i2cclk + frequency - 1.Hz()) / frequency;
 
         // 8192 = 16 * (256 + 256) is the highest scale factor we can achieve
@@ -125,7 +385,12 @@ impl<I2C, PINS> I2c<'_, I2C, PINS> {
 
         macro_rules! timing {
             ($min:expr, $tf:expr, $tr:expr, $su:expr, $ticks:expr, $l_weight:expr, $h_weight:expr, $scll_min:expr, $sclh_min:expr) => {{
-                assert!(i2cclk >= $min.MHz::<1, 1>());
+                if i2cclk < $min.MHz::<1, 1>() {
+                    return Err(ConfigError::KernelClockTooLow {
+                        min: $min.MHz(),
+                        actual: i2cclk,
+                    });
+                }
 
                 // t_sync1 and t_sync2 insert an additional delay of > 2 additional i2cclk cycles and > 50 ns for AF each
                 // To account for the first, we subtract 2*2, to account for the second, we subtract 2*50ns*i2cclk
@@ -134,7 +399,8 @@ impl<I2C, PINS> I2c<'_, I2C, PINS> {
                 let scll_min_ratio = (i2cclk.to_kHz() * $scll_min + 999_999) / 1_000_000;
                 let sclh_min_ratio = (i2cclk.to_kHz() * $sclh_min + 999_999) / 1_000_000;
 
-                let sdadel_ratio = ($tf - 50u32)
+                let sdadel_ratio = $tf
+                    .saturating_sub(50)
                     .checked_sub(3_000_000 / i2cclk.to_kHz())
                     .map(|x| (x * i2cclk.to_kHz() + 999_999) / 1_000_000)
                     .unwrap_or(0);
@@ -154,16 +420,16 @@ impl<I2C, PINS> I2c<'_, I2C, PINS> {
                 let sdadel = ((sdadel_ratio + presc - 1) / presc) as u8;
                 let scldel = ((scldel_ratio - 1) / presc) as u8;
 
-                [presc_reg, scll, sclh, sdadel, scldel]
+                Ok([presc_reg, scll, sclh, sdadel, scldel])
             }};
         }
 
         if frequency > 400.kHz::<1, 1>() {
-            timing!(19, 120, 120, 50, 384, 2, 1, 500, 260)
+            timing!(19, params.fall_ns, params.rise_ns, 50, 384, 2, 1, 500, 260)
         } else if frequency > 100.kHz::<1, 1>() {
-            timing!(9, 300, 300, 100, 384, 2, 1, 1300, 600)
+            timing!(9, params.fall_ns, params.rise_ns, 100, 384, 2, 1, 1300, 600)
         } else {
-            timing!(2, 300, 1000, 250, 512, 1, 1, 4700, 4000)
+            timing!(2, params.fall_ns, params.rise_ns, 250, 512, 1, 1, 4700, 4000)
         }
     }
 }
@@ -178,6 +444,15 @@ pub enum Address {
     TenBit(TenBitAddress),
 }
 
+/// Transfer direction requested by the master, see [`I2c::transfer_direction`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The master writes, the slave receives
+    Write,
+    /// The master reads, the slave transmits
+    Read,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum NextOp {
     Start,
@@ -206,16 +481,37 @@ impl NextOp {
     }
 }
 
-macro_rules! next_op {
-    ($op:expr, $next:expr) => {{
-        if $next.is_none() {
-            NextOp::Stop
-        } else if matches!($op, Operation::Read(_)) == matches!($next, Some(Operation::Read(_))) {
-            NextOp::Reload
-        } else {
-            NextOp::Restart
+/// Start and stop conditions of the operations of a transaction
+///
+/// Consecutive operations in the same direction are joined with RELOAD, a change of direction is
+/// a repeated START. Only the last operation ends with a STOP.
+struct Segments {
+    next_op: NextOp,
+}
+
+impl Segments {
+    const fn new() -> Self {
+        Self {
+            next_op: NextOp::Start,
         }
-    }};
+    }
+
+    /// Conditions of the next operation, `next_read` is the direction of the one following it
+    fn next(&mut self, read: bool, next_read: Option<bool>) -> (Start, Stop) {
+        let start = self.next_op.start();
+
+        self.next_op = match next_read {
+            None => NextOp::Stop,
+            Some(next_read) if next_read == read => NextOp::Reload,
+            Some(_) => NextOp::Restart,
+        };
+
+        (start, self.next_op.stop())
+    }
+}
+
+fn is_read(op: &Operation<'_>) -> bool {
+    matches!(op, Operation::Read(_))
 }
 
 macro_rules! flush_txdr {
@@ -232,10 +528,12 @@ macro_rules! flush_txdr {
 
 macro_rules! busy_wait {
     ($i2c:expr, $flag:ident, $variant:ident, $nack:expr) => {{
-        let isr = $i2c.isr.read();
+        loop {
+            let isr = $i2c.isr.read();
 
-        while !isr.$flag().$variant() {
-            if isr.berr().bit() {
+            if isr.$flag().$variant() {
+                break;
+            } else if isr.berr().bit() {
                 $i2c.icr.write(|w| w.berrcf().set_bit());
                 return Err(Error::Bus);
             } else if isr.arlo().bit() {
@@ -255,27 +553,50 @@ macro_rules! i2c {
         paste! {
             $(
                 impl<'a, PINS> I2c<'a, $I2Cx, PINS> {
-                    pub fn new(i2c: $I2Cx, pins: PINS, clocks: impl Clocks + TrustedClocks<'a>, frequency: Hertz, rec: &mut rec::$I2Cx) -> Result<Self, ConfigError>
+                    /// Creates a new I2C master
+                    ///
+                    /// On error, the peripheral and the pins are handed back to the caller
+                    /// together with the cause, so that another configuration can be tried.
+                    pub fn new(i2c: $I2Cx, pins: PINS, clocks: impl Clocks + TrustedClocks<'a>, frequency: Hertz, rec: &mut rec::$I2Cx) -> Result<Self, (ConfigError, $I2Cx, PINS)>
+                    where
+                        PINS: Pins<$I2Cx>,
+                    {
+                        let params = TimingParams::for_frequency(frequency);
+                        Self::new_with_timing_params(i2c, pins, clocks, frequency, params, rec)
+                    }
+
+                    /// Creates a new I2C master, computing the timings for the given bus rise and fall times
+                    ///
+                    /// [`new`](Self::new) uses the maximum rise and fall times allowed for the speed mode.
+                    /// Buses with long traces or many devices may need larger values.
+                    pub fn new_with_timing_params(i2c: $I2Cx, pins: PINS, clocks: impl Clocks + TrustedClocks<'a>, frequency: Hertz, params: TimingParams, rec: &mut rec::$I2Cx) -> Result<Self, (ConfigError, $I2Cx, PINS)>
                     where
                         PINS: Pins<$I2Cx>,
                     {
                         if frequency > 1.MHz::<1, 1>() {
-                            panic!("Maximum allowed frequency is 1 MHz");
+                            return Err((ConfigError::FrequencyTooHigh, i2c, pins));
                         }
 
                         if 4 * clocks.pclk1() < 3 * frequency {
-                            panic!("PCLK1 frequency must be at least 3/4 of SCL frequency");
+                            return Err((ConfigError::PclkTooLow, i2c, pins));
                         }
 
-                        rec.enable();
-                        rec.reset();
-
                         let i2cclk = match clocks.[<$I2Cx:lower _clk>]() {
                             Some(x) => x,
-                            None => return Err(ConfigError::I2cClockDisabled),
+                            None => return Err((ConfigError::I2cClockDisabled, i2c, pins)),
+                        };
+
+                        let [presc, scll, sclh, sdadel, scldel] = match Self::timings_with_params(i2cclk, frequency, params) {
+                            Ok(x) => x,
+                            Err(err) => return Err((err, i2c, pins)),
                         };
 
-                        let [presc, scll, sclh, sdadel, scldel] = Self::timings(i2cclk, frequency);
+                        if presc > 15 {
+                            return Err((ConfigError::InvalidTimingParams, i2c, pins));
+                        }
+
+                        rec.enable();
+                        rec.reset();
 
                         i2c.timingr.modify(|_, w| {
                             w.presc()
@@ -309,7 +630,10 @@ macro_rules! i2c {
                             Event::NotAcknowledge => self.i2c.cr1.modify(|_, w| w.nackie().set_bit()),
                             Event::Stop => self.i2c.cr1.modify(|_, w| w.stopie().set_bit()),
                             Event::TransferComplete => self.i2c.cr1.modify(|_, w| w.tcie().set_bit()),
-                            Event::Errors => self.i2c.cr1.modify(|_, w| w.errie().set_bit()),
+                            Event::Errors
+                            | Event::Timeout
+                            | Event::PecError
+                            | Event::SmbAlert => self.i2c.cr1.modify(|_, w| w.errie().set_bit()),
                         }
                     }
 
@@ -321,7 +645,10 @@ macro_rules! i2c {
                             Event::NotAcknowledge => self.i2c.cr1.modify(|_, w| w.nackie().clear_bit()),
                             Event::Stop => self.i2c.cr1.modify(|_, w| w.stopie().clear_bit()),
                             Event::TransferComplete => self.i2c.cr1.modify(|_, w| w.tcie().clear_bit()),
-                            Event::Errors => self.i2c.cr1.modify(|_, w| w.errie().clear_bit()),
+                            Event::Errors
+                            | Event::Timeout
+                            | Event::PecError
+                            | Event::SmbAlert => self.i2c.cr1.modify(|_, w| w.errie().clear_bit()),
                         }
                     }
 
@@ -335,10 +662,131 @@ macro_rules! i2c {
                                     .arlocf().set_bit()
                                     .ovrcf().set_bit()
                             }),
+                            Event::Timeout => self.i2c.icr.write(|w| w.timoutcf().set_bit()),
+                            Event::PecError => self.i2c.icr.write(|w| w.peccf().set_bit()),
+                            Event::SmbAlert => self.i2c.icr.write(|w| w.alertcf().set_bit()),
                             _ => (),
                         }
                     }
 
+                    /// Listened events whose flags are set, in the order of [`Event`]
+                    ///
+                    /// Lets an interrupt handler dispatch on the events it enabled with
+                    /// [`listen`](Self::listen).
+                    pub fn pending_events(&self) -> heapless::Vec<Event, 10> {
+                        Event::pending(self.i2c.isr.read().bits(), self.i2c.cr1.read().bits())
+                    }
+
+                    /// A communication is in progress on the bus
+                    pub fn is_busy(&self) -> bool {
+                        self.i2c.isr.read().busy().bit_is_set()
+                    }
+
+                    pub fn flags(&self) -> Flags {
+                        Flags::from_isr(self.i2c.isr.read().bits())
+                    }
+
+                    /// Clears the given flags
+                    ///
+                    /// Only `NACKF` and `STOPF` can be cleared by software, the other flags are
+                    /// cleared by reading / writing the data registers or starting a new transfer
+                    pub fn clear_flags(&mut self, flags: Flags) {
+                        self.i2c.icr.write(|w| {
+                            w.nackcf()
+                                .bit(flags.contains(Flags::NACKF))
+                                .stopcf()
+                                .bit(flags.contains(Flags::STOPF))
+                        });
+                    }
+
+                    /// Sets the own address 1 (OAR1) the peripheral acknowledges in slave mode
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `addr` is out of range
+                    pub fn set_own_address(&mut self, addr: Address) {
+                        let (addr, oa1mode) = match addr {
+                            Address::SevenBit(x) => {
+                                assert!(x < 128);
+                                ((x as u16) << 1, SEVEN_BIT_ADDR_MODE)
+                            }
+                            Address::TenBit(x) => {
+                                assert!(x < 1024);
+                                (x, TEN_BIT_ADDR_MODE)
+                            }
+                        };
+
+                        // OA1 and OA1MODE can only be written while OA1EN is cleared
+                        self.i2c.oar1.modify(|_, w| w.oa1en().clear_bit());
+                        self.i2c.oar1.modify(|_, w| w.oa1().variant(addr).oa1mode().bit(oa1mode));
+                        self.i2c.oar1.modify(|_, w| w.oa1en().set_bit());
+                    }
+
+                    /// Own address 1, `None` if disabled
+                    pub fn own_address(&self) -> Option<Address> {
+                        let oar1 = self.i2c.oar1.read();
+
+                        if oar1.oa1en().bit_is_clear() {
+                            return None;
+                        }
+
+                        if oar1.oa1mode().bit() == TEN_BIT_ADDR_MODE {
+                            Some(Address::TenBit(oar1.oa1().bits()))
+                        } else {
+                            Some(Address::SevenBit((oar1.oa1().bits() >> 1) as u8))
+                        }
+                    }
+
+                    /// Address received from the master on an [`Event::AddressMatch`] (ADDCODE)
+                    ///
+                    /// For a 10-bit address, only the 2 MSBs are part of ADDCODE, the full own
+                    /// address 1 is returned in that case.
+                    pub fn matched_address(&self) -> Address {
+                        let addcode = self.i2c.isr.read().addcode().bits();
+
+                        match self.own_address() {
+                            Some(Address::TenBit(x))
+                                if addcode & 0b111_1100 == 0b111_1000
+                                    && u16::from(addcode & 0b11) == x >> 8 =>
+                            {
+                                Address::TenBit(x)
+                            }
+                            _ => Address::SevenBit(addcode),
+                        }
+                    }
+
+                    /// Direction of the transfer after an [`Event::AddressMatch`] (DIR)
+                    pub fn transfer_direction(&self) -> Direction {
+                        if self.i2c.isr.read().dir().bit_is_set() {
+                            Direction::Read
+                        } else {
+                            Direction::Write
+                        }
+                    }
+
+                    /// Enables or disables clock stretching in slave mode (NOSTRETCH)
+                    ///
+                    /// NOSTRETCH can only be written while the peripheral is disabled, so PE is
+                    /// cleared for the update, which resets the communication state. Fails with
+                    /// [`ConfigError::Busy`] if a communication is in progress.
+                    ///
+                    /// Clock stretching must stay enabled in master mode and for the wakeup from
+                    /// Stop mode on address match. A slave that can't stretch the clock must serve
+                    /// its interrupts before the next SCL edge, otherwise over- or underruns occur.
+                    pub fn set_clock_stretching(&mut self, en: bool) -> Result<(), ConfigError> {
+                        if self.is_busy() {
+                            return Err(ConfigError::Busy);
+                        }
+
+                        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+                        // PE must be kept low during at least 3 APB clock cycles
+                        while self.i2c.cr1.read().pe().bit_is_set() {}
+                        self.i2c.cr1.modify(|_, w| w.nostretch().bit(!en));
+                        self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+
+                        Ok(())
+                    }
+
                     pub fn rx_dma(&mut self, en: bool) {
                         self.i2c.cr1.modify(|_, w| w.rxdmaen().bit(en));
                     }
@@ -347,13 +795,48 @@ macro_rules! i2c {
                         self.i2c.cr1.modify(|_, w| w.txdmaen().bit(en));
                     }
 
+                    /// Releases the peripheral and the pins
+                    ///
+                    /// The peripheral stays enabled and clocked, use [`release`](Self::release) to shut it down.
                     pub fn free(self) -> ($I2Cx, PINS) {
                         (self.i2c, self.pins)
                     }
+
+                    /// Disables the peripheral (PE) and its clock, then releases the peripheral and the pins
+                    ///
+                    /// The pins are returned in their alternate function mode, convert them to inputs or
+                    /// analog to stop driving the bus. Creating a new I2C instance enables the clock again.
+                    pub fn release(self, rec: &mut rec::$I2Cx) -> ($I2Cx, PINS) {
+                        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+                        rec.disable();
+
+                        (self.i2c, self.pins)
+                    }
                 }
 
                 /// Master controller methods
+                ///
+                /// Low-level building blocks of the `embedded-hal` implementation. A transfer consists
+                /// of segments of at most 255 bytes, each started with [`Start`] and ended with [`Stop`].
+                ///
+                /// Writing register `0x10` of a device and reading back 2 bytes after a repeated
+                /// START:
+                ///
+                /// ```ignore
+                /// let addr = Address::SevenBit(0x42);
+                /// let mut buf = [0; 2];
+                ///
+                /// i2c.master_write_bytes(addr, &[0x10], Start::Start, Stop::Software)?;
+                /// i2c.master_read_bytes(addr, &mut buf, Start::Restart, Stop::Automatic)?;
+                /// ```
                 impl<PINS> I2c<'_, $I2Cx, PINS> {
+                    /// Starts a read segment of `len` bytes from `addr`
+                    ///
+                    /// Only programs CR2, the bytes have to be read from RXDR afterwards.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `len` exceeds 255 or `addr` is out of range
                     pub fn master_read(&mut self, addr: Address, len: usize, stop: Stop) {
                         assert!(len < 256);
 
@@ -390,6 +873,13 @@ macro_rules! i2c {
                         });
                     }
 
+                    /// Starts a write segment of `len` bytes to `addr`
+                    ///
+                    /// Only programs CR2, the bytes have to be written to TXDR afterwards.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `len` exceeds 255 or `addr` is out of range
                     pub fn master_write(&mut self, addr: Address, len: usize, stop: Stop) {
                         assert!(len < 256);
 
@@ -426,9 +916,77 @@ macro_rules! i2c {
                         });
                     }
 
+                    /// Writes `buf` to `addr` using DMA
+                    ///
+                    /// Routes the I2C TX request to `channel`, starts the DMA transfer and a master write
+                    /// with automatic STOP. The returned [`DmaTransfer`] completes with the STOP after
+                    /// the DMA has moved all bytes.
+                    pub fn write_dma<const D: u8, const C: u8>(
+                        &mut self,
+                        addr: Address,
+                        channel: Channel<D, C>,
+                        buf: &'static [u8],
+                    ) -> DmaTransfer<'_, Self, D, C, &'static [u8]> {
+                        assert!(buf.len() < 256);
+
+                        let len = buf.len();
+                        let txdr = &self.i2c.txdr as *const _ as u32;
+
+                        // SAFETY: TXDR accepts byte writes
+                        let mut transfer = unsafe {
+                            Transfer::memory_to_peripheral(channel, DmaRequest::[<$I2Cx:camel Tx>], buf, txdr)
+                        };
+                        transfer.start();
+
+                        self.tx_dma(true);
+                        self.master_write(addr, len, Stop::Automatic);
+
+                        DmaTransfer { i2c: self, transfer }
+                    }
+
+                    /// Reads `buf.len()` bytes from `addr` into `buf` using DMA
+                    ///
+                    /// Routes the I2C RX request to `channel`, starts the DMA transfer and a master read
+                    /// with automatic STOP. The returned [`DmaTransfer`] completes with the STOP after
+                    /// the DMA has moved all bytes.
+                    pub fn read_dma<const D: u8, const C: u8>(
+                        &mut self,
+                        addr: Address,
+                        channel: Channel<D, C>,
+                        buf: &'static mut [u8],
+                    ) -> DmaTransfer<'_, Self, D, C, &'static mut [u8]> {
+                        assert!(buf.len() < 256);
+
+                        let len = buf.len();
+                        let rxdr = &self.i2c.rxdr as *const _ as u32;
+
+                        // SAFETY: RXDR supports byte reads
+                        let mut transfer = unsafe {
+                            Transfer::peripheral_to_memory(channel, DmaRequest::[<$I2Cx:camel Rx>], buf, rxdr)
+                        };
+                        transfer.start();
+
+                        self.rx_dma(true);
+                        self.master_read(addr, len, Stop::Automatic);
+
+                        DmaTransfer { i2c: self, transfer }
+                    }
+
+                    /// Repeated START in the opposite direction, with a new segment of `len` bytes
+                    ///
+                    /// Waits for the previous segment to complete (TC). Fails with
+                    /// [`Error::InvalidState`] if the previous segment ended with [`Stop::Reload`].
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `len` exceeds 255
                     pub fn master_restart(&mut self, len: usize, stop: Stop) -> Result<(), Error> {
                         assert!(len < 256);
 
+                        if self.i2c.cr2.read().reload().bit() {
+                            return Err(Error::InvalidState);
+                        }
+
                         let rw = !self.i2c.cr2.read().rd_wrn().bit();
                         let head10r = rw == RD_WRN_READ && self.i2c.cr2.read().add10().bit_is_set() == TEN_BIT_ADDR_MODE;
 
@@ -452,9 +1010,21 @@ macro_rules! i2c {
                         Ok(())
                     }
 
+                    /// Continues the transfer in the same direction with a new segment of `len` bytes
+                    ///
+                    /// Waits for the previous segment to complete (TCR). Fails with
+                    /// [`Error::InvalidState`] unless the previous segment ended with [`Stop::Reload`].
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `len` exceeds 255
                     pub fn master_reload(&mut self, len: usize, stop: Stop) -> Result<(), Error> {
                         assert!(len < 256);
 
+                        if !self.i2c.cr2.read().reload().bit() {
+                            return Err(Error::InvalidState);
+                        }
+
                         busy_wait!(self.i2c, tcr, bit_is_set, NoAcknowledgeSource::Data);
 
                         self.i2c.cr2.modify(|_, w| {
@@ -469,14 +1039,38 @@ macro_rules! i2c {
                         Ok(())
                     }
 
+                    /// Sends a STOP condition, ending a segment that ended with [`Stop::Software`]
                     pub fn master_stop(&mut self) {
                         self.i2c.cr2.modify(|_, w| w.stop().set_bit());
                     }
 
+                    /// Leaves the bus idle after a failed transfer and passes `err` on
+                    fn recover(&mut self, err: Error) -> Error {
+                        match Recovery::after(err, self.i2c.isr.read().bits()) {
+                            Recovery::Idle => {}
+                            Recovery::Stop => self.master_stop(),
+                            Recovery::Reset => {
+                                self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+                                // PE must be kept low during at least 3 APB clock cycles
+                                while self.i2c.cr1.read().pe().bit_is_set() {}
+                                self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+                            }
+                        }
+
+                        flush_txdr!(self.i2c);
+
+                        err
+                    }
+
+                    /// Writes `bytes` to `addr`, split into segments of 255 bytes
+                    ///
+                    /// [`Start::Restart`] requires the previous segment to be a read, [`Start::Reload`] a
+                    /// write, otherwise [`Error::InvalidState`] is returned.
                     pub fn master_write_bytes(&mut self, addr: Address, bytes: &[u8], start: Start, stop: Stop) -> Result<(), Error> {
                         let mut begin = true;
                         let mut rem = bytes.len();
-                        let mut iter = bytes.chunks(255);
+                        // An empty write still sends the address, as a single segment with NBYTES = 0
+                        let mut iter = bytes.chunks(255).chain(bytes.is_empty().then_some(bytes));
 
                         for chunk in &mut iter {
                             let stp = if rem > 255 {
@@ -491,12 +1085,16 @@ macro_rules! i2c {
                                 match start {
                                     Start::Start => self.master_write(addr, chunk.len(), stp),
                                     Start::Restart => {
-                                        assert!(self.i2c.cr2.read().rd_wrn().bit() == RD_WRN_READ);
+                                        if self.i2c.cr2.read().rd_wrn().bit() != RD_WRN_READ {
+                                            return Err(Error::InvalidState);
+                                        }
 
                                         self.master_restart(chunk.len(), stp)?;
                                     }
                                     Start::Reload => {
-                                        assert!(self.i2c.cr2.read().rd_wrn().bit() == RD_WRN_WRITE);
+                                        if self.i2c.cr2.read().rd_wrn().bit() != RD_WRN_WRITE {
+                                            return Err(Error::InvalidState);
+                                        }
 
                                         self.master_reload(chunk.len(), stp)?;
                                     }
@@ -510,12 +1108,57 @@ macro_rules! i2c {
                         }
 
                         if stop == Stop::Automatic {
-                            busy_wait!(self.i2c, tc, bit_is_set, NoAcknowledgeSource::Data);
+                            let nack = if bytes.is_empty() {
+                                NoAcknowledgeSource::Address
+                            } else {
+                                NoAcknowledgeSource::Data
+                            };
+
+                            self.wait_stop(nack)?;
                         }
 
                         Ok(())
                     }
 
+                    /// Checks whether a device acknowledges `addr`
+                    ///
+                    /// Sends START, the address with the write bit and STOP, without any data
+                    /// (NBYTES = 0). Returns `Ok(false)` if the address was not acknowledged.
+                    pub fn ping(&mut self, addr: Address) -> Result<bool, Error> {
+                        match self.master_write_bytes(addr, &[], Start::Start, Stop::Automatic) {
+                            Ok(()) => Ok(true),
+                            Err(Error::NoAcknowledge(NoAcknowledgeSource::Address)) => Ok(false),
+                            Err(err) => Err(err),
+                        }
+                    }
+
+                    /// Waits for the STOP condition ending a segment with [`Stop::Automatic`]
+                    ///
+                    /// TC is never set with AUTOEND. On a NACK, the hardware sends the STOP on its own,
+                    /// NACKF is checked first since both flags may already be set.
+                    fn wait_stop(&mut self, nack: NoAcknowledgeSource) -> Result<(), Error> {
+                        loop {
+                            let isr = self.i2c.isr.read();
+
+                            if isr.nackf().bit() {
+                                while self.i2c.isr.read().stopf().bit_is_clear() {}
+                                self.i2c.icr.write(|w| w.nackcf().set_bit().stopcf().set_bit());
+                                flush_txdr!(self.i2c);
+                                return Err(Error::NoAcknowledge(nack));
+                            } else if isr.stopf().bit() {
+                                self.i2c.icr.write(|w| w.stopcf().set_bit());
+                                return Ok(());
+                            } else if isr.berr().bit() {
+                                self.i2c.icr.write(|w| w.berrcf().set_bit());
+                                return Err(Error::Bus);
+                            } else if isr.arlo().bit() {
+                                self.i2c.icr.write(|w| w.arlocf().set_bit());
+                                return Err(Error::Arbitration);
+                            }
+                        }
+                    }
+
+                    /// Like [`master_write_bytes`](Self::master_write_bytes), one byte per segment
                     pub fn master_write_bytes_iter<B>(&mut self, addr: Address, bytes: B, start: Start, stop: Stop) -> Result<(), Error>
                     where
                         B: IntoIterator<Item = u8>,
@@ -564,6 +1207,10 @@ macro_rules! i2c {
                         Ok(())
                     }
 
+                    /// Reads into `buffer` from `addr`, split into segments of 255 bytes
+                    ///
+                    /// [`Start::Restart`] requires the previous segment to be a write, [`Start::Reload`] a
+                    /// read, otherwise [`Error::InvalidState`] is returned.
                     pub fn master_read_bytes(&mut self, addr: Address, buffer: &mut [u8], start: Start, stop: Stop) -> Result<(), Error> {
                         let mut begin = true;
                         let mut rem = buffer.len();
@@ -582,12 +1229,16 @@ macro_rules! i2c {
                                 match start {
                                     Start::Start => self.master_read(addr, chunk.len(), stp),
                                     Start::Restart => {
-                                        assert!(self.i2c.cr2.read().rd_wrn().bit() == RD_WRN_WRITE);
+                                        if self.i2c.cr2.read().rd_wrn().bit() != RD_WRN_WRITE {
+                                            return Err(Error::InvalidState);
+                                        }
 
                                         self.master_restart(chunk.len(), stp)?;
                                     }
                                     Start::Reload => {
-                                        assert!(self.i2c.cr2.read().rd_wrn().bit() == RD_WRN_READ);
+                                        if self.i2c.cr2.read().rd_wrn().bit() != RD_WRN_READ {
+                                            return Err(Error::InvalidState);
+                                        }
 
                                         self.master_reload(chunk.len(), stp)?;
                                     }
@@ -601,12 +1252,63 @@ macro_rules! i2c {
                         }
 
                         if stop == Stop::Automatic {
-                            busy_wait!(self.i2c, tc, bit_is_set, NoAcknowledgeSource::Data);
+                            self.wait_stop(NoAcknowledgeSource::Data)?;
                         }
 
                         Ok(())
                     }
 
+                    /// Reads a variable-length block from `addr`, e.g. an SMBus block read
+                    ///
+                    /// The first `first_len` bytes are acknowledged without a STOP (RELOAD), then `then`
+                    /// gets the last of them and returns the number of bytes still to read. These are read
+                    /// into the rest of `buffer`, and the transfer ends with NACK and STOP. Returns the
+                    /// total number of bytes read.
+                    ///
+                    /// If `then` returns 0, or more than the rest of `buffer` holds, a dummy byte is read
+                    /// to end the transfer, as the device already got an ACK for the last byte. The latter
+                    /// fails with [`Error::Overflow`].
+                    ///
+                    /// ```ignore
+                    /// // SMBus block read: command code, then the byte count followed by the data
+                    /// i2c.master_write_bytes(addr, &[cmd], Start::Start, Stop::Software)?;
+                    /// let n = i2c.master_read_dynamic(addr, &mut buf, 1, Start::Restart, |count| count as usize)?;
+                    /// ```
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `first_len` is 0 or exceeds the length of `buffer`
+                    pub fn master_read_dynamic(
+                        &mut self,
+                        addr: Address,
+                        buffer: &mut [u8],
+                        first_len: usize,
+                        start: Start,
+                        mut then: impl FnMut(u8) -> usize,
+                    ) -> Result<usize, Error> {
+                        assert!(first_len > 0 && first_len <= buffer.len());
+
+                        let (head, tail) = buffer.split_at_mut(first_len);
+                        self.master_read_bytes(addr, head, start, Stop::Reload)?;
+
+                        let rest = then(head[first_len - 1]);
+
+                        if rest == 0 || rest > tail.len() {
+                            let mut dummy = [0];
+                            self.master_read_bytes(addr, &mut dummy, Start::Reload, Stop::Automatic)?;
+
+                            return if rest == 0 {
+                                Ok(first_len)
+                            } else {
+                                Err(Error::Overflow)
+                            };
+                        }
+
+                        self.master_read_bytes(addr, &mut tail[..rest], Start::Reload, Stop::Automatic)?;
+
+                        Ok(first_len + rest)
+                    }
+
                     fn read_bytes<'a, B>(&mut self, buffer: B, mut first: bool) -> Result<(), Error>
                     where
                         B: IntoIterator<Item = &'a mut u8>,
@@ -628,9 +1330,52 @@ macro_rules! i2c {
                     }
                 }
 
+                impl<'a, PINS, const D: u8, const C: u8, BUF> DmaTransfer<'_, I2c<'a, $I2Cx, PINS>, D, C, BUF> {
+                    /// The DMA has moved all bytes (TCIF), the STOP may still be pending
+                    pub fn is_complete(&self) -> bool {
+                        self.transfer.is_complete()
+                    }
+
+                    /// Blocks until the transfer has ended with a STOP, then releases the channel and the
+                    /// buffer
+                    ///
+                    /// A NACK, a bus error or an arbitration loss aborts the DMA transfer, as does a DMA
+                    /// transfer error. The channel and the buffer are handed back with the error, the bus
+                    /// is left idle. The DMA requests of the I2C are disabled in any case.
+                    pub fn wait(mut self) -> Result<(Channel<D, C>, BUF), (Error, Channel<D, C>, BUF)> {
+                        loop {
+                            let isr = self.i2c.i2c.isr.read();
+
+                            if isr.nackf().bit() || isr.berr().bit() || isr.arlo().bit() {
+                                break;
+                            } else if self.transfer.is_complete() || self.transfer.has_error() {
+                                break;
+                            }
+                        }
+
+                        let dma_error = self.transfer.has_error();
+                        let (channel, buf) = self.transfer.abort();
+
+                        self.i2c.tx_dma(false);
+                        self.i2c.rx_dma(false);
+
+                        let result = if dma_error {
+                            Err(Error::Dma)
+                        } else {
+                            // The DMA doesn't tell whether the address or a data byte was NACKed
+                            self.i2c.wait_stop(NoAcknowledgeSource::Unknown)
+                        };
+
+                        match result {
+                            Ok(()) => Ok((channel, buf)),
+                            Err(err) => Err((self.i2c.recover(err), channel, buf)),
+                        }
+                    }
+                }
+
                 macro_rules! hal {
                     ($addr:ty, $variant:ident) => {
-                        impl<PINS> embedded_hal::i2c::blocking::I2c<$addr> for I2c<'_, $I2Cx, PINS> {
+                        impl<PINS> embedded_hal::i2c::I2c<$addr> for I2c<'_, $I2Cx, PINS> {
                             fn read(&mut self, addr: $addr, buffer: &mut [u8]) -> Result<(), Self::Error> {
                                 self.master_read_bytes(Address::$variant(addr), buffer, Start::Start, Stop::Automatic)
                             }
@@ -639,37 +1384,15 @@ macro_rules! i2c {
                                 self.master_write_bytes(Address::$variant(addr), bytes, Start::Start, Stop::Automatic)
                             }
 
-                            fn write_iter<B>(&mut self, addr: $addr, bytes: B) -> Result<(), Self::Error>
-                            where
-                                B: core::iter::IntoIterator<Item = u8>,
-                            {
-                                self.master_write_bytes_iter(Address::$variant(addr), bytes, Start::Start, Stop::Automatic)
-                            }
-
                             fn write_read(&mut self, addr: $addr, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
                                 let addr = Address::$variant(addr);
 
-                                self.master_write_bytes(addr, bytes, Start::Start, Stop::Software)?;
-
-                                self.master_read_bytes(addr, buffer, Start::Restart, Stop::Automatic)?;
-
-                                Ok(())
+                                self.master_write_bytes(addr, bytes, Start::Start, Stop::Software)
+                                    .and_then(|_| self.master_read_bytes(addr, buffer, Start::Restart, Stop::Automatic))
+                                    .map_err(|err| self.recover(err))
                             }
 
-                            fn write_iter_read<B>(&mut self, addr: $addr, bytes: B, buffer: &mut [u8]) -> Result<(), Self::Error>
-                            where
-                                B: core::iter::IntoIterator<Item = u8>,
-                            {
-                                let addr = Address::$variant(addr);
-
-                                self.master_write_bytes_iter(addr, bytes, Start::Restart, Stop::Software)?;
-
-                                self.master_read_bytes(addr, buffer, Start::Restart, Stop::Automatic)?;
-
-                                Ok(())
-                            }
-
-                            fn transaction<'a>(&mut self, addr: $addr, operations: &mut [Operation<'a>]) -> Result<(), Self::Error> {
+                            fn transaction(&mut self, addr: $addr, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
                                 let len = operations.len();
                                 let addr = Address::$variant(addr);
 
@@ -677,51 +1400,18 @@ macro_rules! i2c {
                                     return Ok(());
                                 }
 
-                                let mut next_op = NextOp::Start;
+                                let mut segments = Segments::new();
 
                                 for i in 0..len {
-                                    let current_op = next_op;
-                                    let next = if i + 1 < len {
-                                        Some(&operations[i + 1])
-                                    } else {
-                                        None
-                                    };
-
-                                    next_op = next_op!(operations[i], next);
+                                    let next_read = operations.get(i + 1).map(is_read);
+                                    let (start, stop) = segments.next(is_read(&operations[i]), next_read);
 
                                     match &mut operations[i] {
                                         Operation::Read(buf) => {
-                                            self.master_read_bytes(addr, buf, current_op.start(), next_op.stop())?;
-                                        }
-                                        Operation::Write(bytes) => {
-                                            self.master_write_bytes(addr, bytes, current_op.start(), next_op.stop())?;
-                                        }
-                                    }
-                                }
-
-                                Ok(())
-                            }
-
-                            fn transaction_iter<'a, O>(&mut self, addr: $addr, operations: O) -> Result<(), Self::Error>
-                            where
-                                O: core::iter::IntoIterator<Item = Operation<'a>>,
-                            {
-                                let addr = Address::$variant(addr);
-                                let mut iter = operations.into_iter();
-                                let mut next = iter.next();
-                                let mut next_op = NextOp::Start;
-
-                                while let Some(current) = next {
-                                    next = iter.next();
-                                    let current_op = next_op;
-                                    next_op = next_op!(current, next);
-
-                                    match current {
-                                        Operation::Read(buf) => {
-                                            self.master_read_bytes(addr, buf, current_op.start(), next_op.stop())?;
+                                            self.master_read_bytes(addr, buf, start, stop)?;
                                         }
                                         Operation::Write(bytes) => {
-                                            self.master_write_bytes(addr, bytes, current_op.start(), next_op.stop())?;
+                                            self.master_write_bytes(addr, bytes, start, stop)?;
                                         }
                                     }
                                 }
@@ -738,7 +1428,7 @@ macro_rules! i2c {
                 impl I2cExt for $I2Cx {
                     type REC = rec::$I2Cx;
 
-                    fn i2c<'a, PINS>(self, pins: PINS, clocks: impl Clocks + TrustedClocks<'a>, frequency: Hertz, rec: &mut rec::$I2Cx) -> Result<I2c<'a, $I2Cx, PINS>, ConfigError>
+                    fn i2c<'a, PINS>(self, pins: PINS, clocks: impl Clocks + TrustedClocks<'a>, frequency: Hertz, rec: &mut rec::$I2Cx) -> Result<I2c<'a, $I2Cx, PINS>, (ConfigError, $I2Cx, PINS)>
                     where
                         PINS: Pins<$I2Cx>,
                     {
@@ -752,52 +1442,83 @@ macro_rules! i2c {
 
 i2c! { I2C1, I2C3 }
 
-macro_rules! pins {
-    ($($I2Cx:ty: (
-        SCL: [
-            $($scl:ident),*
-        ]
-        SDA: [
-            $($sda:ident),*
-        ]
-    )),*) => {
-        $(
-            $(
-                #[sealed]
-                impl SclPin<$I2Cx> for crate::gpio::$scl<crate::gpio::Alternate<4, crate::gpio::OpenDrain>> {}
-            )*
-            $(
-                #[sealed]
-                impl SdaPin<$I2Cx> for crate::gpio::$sda<crate::gpio::Alternate<4, crate::gpio::OpenDrain>> {}
-            )*
-        )*
-    };
-}
-
-pins! {
-    I2C1: (
-        SCL: [
-            PA9, PB6, PB8
-        ]
-        SDA: [
-            PA10, PB7, PB9
-        ]
-    ),
-    I2C3: (
-        SCL: [
-            PA7, PB10, PB13, PC0
-        ]
-        SDA: [
-            PB4, PB11, PB14, PC1
-        ]
-    )
-}
-
 #[cfg(all(test, not(test)))]
 mod test {
-    use super::I2c;
+    use super::{
+        ConfigError, Error, Event, Flags, I2c, Recovery, Segments, Start, Stop, TimingParams,
+    };
+    use embedded_hal::i2c::NoAcknowledgeSource;
     use fugit::RateExtU32;
 
+    #[test]
+    /// Test that only the modeled flags are extracted from a raw ISR value
+    fn flags_from_isr() {
+        // TXE | TXIS | NACKF | TC | BERR | BUSY | DIR
+        let isr = (1 << 0) | (1 << 1) | (1 << 4) | (1 << 6) | (1 << 8) | (1 << 15) | (1 << 16);
+        let flags = Flags::from_isr(isr);
+
+        assert_eq!(flags, Flags::TXIS | Flags::NACKF | Flags::TC);
+        assert!(flags.contains(Flags::TXIS | Flags::TC));
+        assert!(!flags.contains(Flags::RXNE));
+        assert!(Flags::from_isr(1 << 15).is_empty());
+    }
+
+    #[test]
+    /// Test that only listened events with a set flag are pending
+    fn pending_events() {
+        // TXIS | TCR | ARLO | BUSY
+        let isr = (1 << 1) | (1 << 7) | (1 << 9) | (1 << 15);
+        // TXIE | TCIE | ERRIE
+        let cr1 = (1 << 1) | (1 << 6) | (1 << 7);
+
+        assert_eq!(
+            Event::pending(isr, cr1).as_slice(),
+            &[Event::Transmit, Event::TransferComplete, Event::Errors]
+        );
+
+        // Not listened
+        assert!(Event::pending(isr, 0).is_empty());
+        // STOPF with STOPIE
+        assert_eq!(Event::pending(1 << 5, 1 << 5).as_slice(), &[Event::Stop]);
+    }
+
+    #[test]
+    /// Test that the SMBus events are reported apart from the bus errors, through ERRIE
+    fn pending_smbus_events() {
+        // PECERR | TIMEOUT | ALERT
+        let isr = (1 << 11) | (1 << 12) | (1 << 13);
+
+        assert_eq!(
+            Event::pending(isr, 1 << 7).as_slice(),
+            &[Event::Timeout, Event::PecError, Event::SmbAlert]
+        );
+        assert!(Event::pending(isr, !(1 << 7)).is_empty());
+        // ARLO
+        assert_eq!(Event::pending(1 << 9, 1 << 7).as_slice(), &[Event::Errors]);
+    }
+
+    #[test]
+    /// Test the bus cleanup after a failed transfer
+    fn recovery_after_error() {
+        const NACKF: u32 = 1 << 4;
+        const TC: u32 = 1 << 6;
+        const BUSY: u32 = 1 << 15;
+
+        // NACK on the address byte, the hardware sends the STOP
+        let nack = Error::NoAcknowledge(NoAcknowledgeSource::Address);
+        assert_eq!(Recovery::after(nack, NACKF | BUSY), Recovery::Idle);
+
+        // Write phase done with Stop::Software, the read phase failed to start
+        assert_eq!(
+            Recovery::after(Error::InvalidState, TC | BUSY),
+            Recovery::Stop
+        );
+
+        // Bus error in the middle of a segment
+        assert_eq!(Recovery::after(Error::Bus, BUSY), Recovery::Reset);
+        assert_eq!(Recovery::after(Error::Bus, 0), Recovery::Idle);
+    }
+
     /// Runs a timing testcase over PCLK and I2C clock ranges
     fn i2c_timing_testcase<F>(f: F)
     where
@@ -826,7 +1547,12 @@ mod test {
     /// Test the SCL frequency is within the expected range
     fn i2c_frequency() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let [presc_reg, scll, sclh, _, _] = I2c::<(), ()>::timings(i2c_clk.Hz(), freq.Hz());
+            let [presc_reg, scll, sclh, _, _] = I2c::<(), ()>::timings_with_params(
+                i2c_clk.Hz(),
+                freq.Hz(),
+                TimingParams::for_frequency(freq.Hz()),
+            )
+            .unwrap();
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -865,7 +1591,12 @@ mod test {
     /// Test that the low period of SCL is greater than the minimum specification
     fn i2c_scl_low() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let [presc_reg, scll, _, _, _] = I2c::<(), ()>::timings(i2c_clk.Hz(), freq.Hz());
+            let [presc_reg, scll, _, _, _] = I2c::<(), ()>::timings_with_params(
+                i2c_clk.Hz(),
+                freq.Hz(),
+                TimingParams::for_frequency(freq.Hz()),
+            )
+            .unwrap();
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -890,7 +1621,12 @@ mod test {
     /// Test that the high period of SCL is greater than the minimum specification
     fn i2c_scl_high() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let [presc_reg, _, sclh, _, _] = I2c::<(), ()>::timings(i2c_clk.Hz(), freq.Hz());
+            let [presc_reg, _, sclh, _, _] = I2c::<(), ()>::timings_with_params(
+                i2c_clk.Hz(),
+                freq.Hz(),
+                TimingParams::for_frequency(freq.Hz()),
+            )
+            .unwrap();
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -915,7 +1651,12 @@ mod test {
     /// Test the SDADEL value is greater than the minimum specification
     fn i2c_sdadel_minimum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let [presc_reg, _, _, sdadel, _] = I2c::<(), ()>::timings(i2c_clk.Hz(), freq.Hz());
+            let [presc_reg, _, _, sdadel, _] = I2c::<(), ()>::timings_with_params(
+                i2c_clk.Hz(),
+                freq.Hz(),
+                TimingParams::for_frequency(freq.Hz()),
+            )
+            .unwrap();
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
             let t_i2c_clk = 1. / (i2c_clk as f32);
@@ -951,7 +1692,12 @@ mod test {
     /// Test the SDADEL value is less than the maximum specification
     fn i2c_sdadel_maximum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let [presc_reg, _, _, sdadel, _] = I2c::<(), ()>::timings(i2c_clk.Hz(), freq.Hz());
+            let [presc_reg, _, _, sdadel, _] = I2c::<(), ()>::timings_with_params(
+                i2c_clk.Hz(),
+                freq.Hz(),
+                TimingParams::for_frequency(freq.Hz()),
+            )
+            .unwrap();
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
             let t_i2c_clk = 1. / (i2c_clk as f32);
@@ -984,7 +1730,12 @@ mod test {
     /// Test the SCLDEL value is greater than the minimum specification
     fn i2c_scldel_minimum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let [presc_reg, _, _, _, scldel_reg] = I2c::<(), ()>::timings(i2c_clk.Hz(), freq.Hz());
+            let [presc_reg, _, _, _, scldel_reg] = I2c::<(), ()>::timings_with_params(
+                i2c_clk.Hz(),
+                freq.Hz(),
+                TimingParams::for_frequency(freq.Hz()),
+            )
+            .unwrap();
             let scldel = scldel_reg + 1;
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -1018,4 +1769,104 @@ mod test {
             assert!(t_scldel >= t_scldel_minimum);
         });
     }
+
+    /// Start and stop conditions of a transaction, `true` for a read
+    fn segments(ops: &[bool]) -> heapless::Vec<(Start, Stop), 8> {
+        let mut segments = Segments::new();
+
+        ops.iter()
+            .enumerate()
+            .map(|(i, &read)| segments.next(read, ops.get(i + 1).copied()))
+            .collect()
+    }
+
+    #[test]
+    /// Test that a transaction has exactly one STOP, at the end, and a repeated START on each
+    /// change of direction
+    fn transaction_segments() {
+        const W: bool = false;
+        const R: bool = true;
+
+        assert_eq!(segments(&[W]), [(Start::Start, Stop::Automatic)]);
+        assert_eq!(segments(&[R]), [(Start::Start, Stop::Automatic)]);
+
+        assert_eq!(
+            segments(&[W, R, R]),
+            [
+                (Start::Start, Stop::Software),
+                (Start::Restart, Stop::Reload),
+                (Start::Reload, Stop::Automatic),
+            ]
+        );
+
+        assert_eq!(
+            segments(&[R, W]),
+            [
+                (Start::Start, Stop::Software),
+                (Start::Restart, Stop::Automatic),
+            ]
+        );
+
+        assert_eq!(
+            segments(&[W, W, R, W]),
+            [
+                (Start::Start, Stop::Reload),
+                (Start::Reload, Stop::Software),
+                (Start::Restart, Stop::Software),
+                (Start::Restart, Stop::Automatic),
+            ]
+        );
+    }
+
+    #[test]
+    /// Test that a kernel clock below the speed mode minimum is reported instead of panicking
+    fn i2c_kernel_clock_too_low() {
+        let params = TimingParams::for_frequency(100.kHz());
+        let res = I2c::<(), ()>::timings_with_params(1.MHz(), 100.kHz(), params);
+
+        assert!(matches!(
+            res,
+            Err(ConfigError::KernelClockTooLow { min, actual })
+                if min == 2.MHz::<1, 1>() && actual == 1.MHz::<1, 1>()
+        ));
+
+        assert!(I2c::<(), ()>::timings_with_params(2.MHz(), 100.kHz(), params).is_ok());
+
+        let params = TimingParams::for_frequency(1.MHz());
+        assert!(I2c::<(), ()>::timings_with_params(16.MHz(), 1.MHz(), params).is_err());
+    }
+
+    #[test]
+    /// Test that slow rise times are honored by SCLDEL without exceeding the bus frequency
+    fn i2c_slow_rise() {
+        let params = TimingParams {
+            rise_ns: 800,
+            fall_ns: 300,
+        };
+
+        for i2c_clk in [16_000_000u32, 32_000_000, 64_000_000] {
+            for freq in (100_000u32..=400_000).step_by(10_000) {
+                let [presc_reg, scll, sclh, _, scldel_reg] =
+                    I2c::<(), ()>::timings_with_params(i2c_clk.Hz(), freq.Hz(), params).unwrap();
+
+                let presc = (presc_reg + 1) as f32;
+                let t_i2c_clk = 1. / (i2c_clk as f32);
+                let t_scldel = (scldel_reg as f32 + 1.) * presc * t_i2c_clk;
+                let t_sudat_min = 100e-9; // Fast mode (Fm)
+
+                println!(
+                    "Clock = {}: Target {} Hz; SCLDEL {}",
+                    i2c_clk, freq, scldel_reg
+                );
+                assert!(presc_reg <= 15);
+                assert!(scldel_reg <= 15);
+                assert!(t_scldel >= 800e-9 + t_sudat_min);
+
+                let t_sync = 2. * (50e-9 + 2. * t_i2c_clk);
+                let t_high_low = sclh as f32 + 1. + scll as f32 + 1.;
+                let f_scl = 1. / (t_sync + t_high_low * presc * t_i2c_clk);
+                assert!(f_scl <= 1.02 * freq as f32);
+            }
+        }
+    }
 }
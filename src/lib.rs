@@ -6,7 +6,9 @@
 mod macros;
 
 pub mod cpu;
-// pub mod delay;
+pub mod crc;
+pub mod delay;
+pub mod dma;
 pub mod flash;
 pub mod gpio;
 pub mod i2c;
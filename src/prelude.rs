@@ -1,3 +1,5 @@
+pub use crate::delay::DelayExt as stm32wb55xx_hal_delay_DelayExt;
+pub use crate::dma::DmaExt as stm32wb55xx_hal_dma_DmaExt;
 pub use crate::flash::FlashExt as stm32wb55xx_hal_flash_FlashExt;
 pub use crate::i2c::I2cExt as stm32wb55xx_hal_i2c_I2cExt;
 pub use crate::pwr::PwrExt as stm32wb55xx_hal_pwr_PwrExt;
@@ -43,10 +43,24 @@
 //! guaranteed in case of a power voltage drop
 //!
 //! Note: Stop, Standby and Shutdown Modes are only entered, when both CPUs are in CStop mode
-
+//!
+//! [`Pwr::transition_to`] moves between the modes described by [`PowerMode`], enforcing the
+//! following rules:
+//!
+//! - LPRun is only entered with a system clock of at most 2 MHz
+//! - Range 2 is only selected with a system clock of at most 2 MHz
+//! - Stop2 can't be entered from LPRun, Stop1 must be used instead
+//! - Stop, Standby and Shutdown aren't entered during flash operations or voltage scaling
+//! - Leaving LPRun waits for the main regulator before the voltage range is changed
+
+use crate::cpu::Cpu;
+use crate::gpio::Pull;
+use crate::pac::pwr::pdcrc::PDCRC_SPEC;
+use crate::pac::pwr::pucrb::PUCRB_SPEC;
 use crate::pac::pwr::{sr1, sr2};
 use crate::pac::PWR;
 use crate::rcc::{self, Clocks, TrustedClocks};
+use crate::time::Hertz;
 use cortex_m::peripheral::SCB;
 use fugit::RateExtU32;
 use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
@@ -56,6 +70,12 @@ pub enum Error {
     SysclkTooHighVos,
     SysclkTooHighLpr,
     LPRunToStop2Illegal,
+    /// A flash program / erase operation is ongoing
+    FlashBusy,
+    /// The regulator is still switching to the selected voltage range (VOSF)
+    VoltageScalingOngoing,
+    /// LPSleep can only be entered from LPRun
+    NotInLowPowerRun,
 }
 
 pub trait PwrExt {
@@ -98,11 +118,71 @@ impl Pwr {
         Ok(())
     }
 
-    pub fn shutdown(&self, scb: &mut SCB) -> ! {
-        let _ = self.enter_low_power_mode(Lpms::Shutdown, scb);
+    /// Prepares a clock increase to an HCLK4 of `hclk4`
+    ///
+    /// Selects Range 1 and waits for the regulator, then raises the flash latency for `hclk4`.
+    /// The latency is never lowered below the one required by the current clocks, so they stay
+    /// valid until SYSCLK / the PLL is raised afterwards.
+    pub fn prepare_for_frequency<'a>(
+        &mut self,
+        hclk4: Hertz,
+        clocks: impl Clocks + TrustedClocks<'a>,
+    ) -> nb::Result<(), Error> {
+        let current = clocks.hclk4();
+
+        self.set_power_range(Vos::Range1, clocks)?;
+
+        rcc::set_flash_latency(current.max(hclk4));
+
+        Ok(())
+    }
+
+    /// Power mode the system is currently in
+    ///
+    /// As code is running, this is either Run or LPRun. The low power modes are only observed
+    /// through [`transition_to`](Self::transition_to).
+    pub fn current_mode(&self) -> PowerMode {
+        if self.lp_run() {
+            PowerMode::LowPowerRun
+        } else {
+            PowerMode::Run {
+                vos: self.power_range(),
+            }
+        }
+    }
 
-        // Technically unreachable
-        unsafe { core::hint::unreachable_unchecked() }
+    /// Moves the system to `mode`, see the module documentation for the legal transitions
+    ///
+    /// For the Stop modes, this function returns after wakeup, in the mode it was called from.
+    /// Standby and Shutdown are left through a reset, this function only returns if they can't
+    /// be entered.
+    pub fn transition_to<'a>(
+        &mut self,
+        mode: PowerMode,
+        clocks: impl Clocks + TrustedClocks<'a>,
+        scb: &mut SCB,
+    ) -> Result<(), Error> {
+        match mode {
+            PowerMode::Run { vos } => {
+                if self.lp_run() {
+                    self.exit_low_power_run();
+                }
+
+                nb::block!(self.set_power_range(vos, clocks))
+            }
+            PowerMode::LowPowerRun => nb::block!(self.enter_low_power_run(clocks)),
+            PowerMode::Stop0 => self.enter_stop(StopMode::Stop0, scb),
+            PowerMode::Stop1 => self.enter_stop(StopMode::Stop1, scb),
+            PowerMode::Stop2 => self.enter_stop(StopMode::Stop2, scb),
+            PowerMode::Standby => {
+                self.can_enter(Lpms::Standby)?;
+                self.enter_standby(scb)
+            }
+            PowerMode::Shutdown => {
+                self.can_enter(Lpms::Shutdown)?;
+                self.enter_shutdown(scb)
+            }
+        }
     }
 
     /// Enter low power mode with enabled flash
@@ -138,22 +218,104 @@ impl Pwr {
         while self.pwr.sr2.read().reglpf().bit_is_set() {}
     }
 
-    /// Enter low power mode
+    /// Checks the preconditions for entering the low power mode `mode`
     ///
-    /// This function doesn't return when no error occured
-    pub fn enter_low_power_mode(&self, mode: Lpms, scb: &mut SCB) -> Result<(), Error> {
-        let cr1 = &c1_c2!(self.pwr.cr1, self.pwr.c2cr1);
-
+    /// - Stop2 can't be entered from LPRun, Stop1 must be used instead
+    /// - No flash program / erase operation must be ongoing
+    /// - The regulator must have reached the selected voltage range
+    ///
+    /// Note: The system only enters Stop, Standby or Shutdown when both CPUs are in CStop mode,
+    /// this can't be checked in advance.
+    pub fn can_enter(&self, mode: Lpms) -> Result<(), Error> {
         if self.pwr.cr1.read().lpr().bit_is_set() && mode == Lpms::Stop2 {
             return Err(Error::LPRunToStop2Illegal);
         }
 
+        // SAFETY: Read-only access
+        let flash = unsafe { &*crate::pac::FLASH::PTR };
+
+        if c1_c2!(flash.sr.read().bsy(), flash.c2sr.read().bsy()).bit_is_set() {
+            return Err(Error::FlashBusy);
+        }
+
+        if self.pwr.sr2.read().vosf().bit_is_set() {
+            return Err(Error::VoltageScalingOngoing);
+        }
+
+        Ok(())
+    }
+
+    /// Enter Stop mode
+    ///
+    /// This function returns when the system is woken up, with the clock configuration described
+    /// in the module documentation.
+    pub fn enter_stop(&self, mode: StopMode, scb: &mut SCB) -> Result<(), Error> {
+        self.can_enter(mode.into())?;
+
+        self.deep_sleep(mode.into(), scb);
+
+        scb.clear_sleepdeep();
+
+        Ok(())
+    }
+
+    /// Enter Standby mode
+    ///
+    /// Waits for ongoing flash operations and voltage scaling to finish first. Standby is left
+    /// through a reset, so this function never returns.
+    pub fn enter_standby(&self, scb: &mut SCB) -> ! {
+        self.enter_no_return(Lpms::Standby, scb)
+    }
+
+    /// Enter Shutdown mode
+    ///
+    /// Waits for ongoing flash operations and voltage scaling to finish first. Shutdown is left
+    /// through a reset, so this function never returns.
+    pub fn enter_shutdown(&self, scb: &mut SCB) -> ! {
+        self.enter_no_return(Lpms::Shutdown, scb)
+    }
+
+    fn enter_no_return(&self, mode: Lpms, scb: &mut SCB) -> ! {
+        while self.can_enter(mode).is_err() {}
+
+        // The system only enters the mode once the other CPU is in CStop too, until then
+        // interrupts wake this CPU up again
+        loop {
+            self.deep_sleep(mode, scb);
+        }
+    }
+
+    fn deep_sleep(&self, mode: Lpms, scb: &mut SCB) {
+        let cr1 = &c1_c2!(self.pwr.cr1, self.pwr.c2cr1);
+
         cr1.modify(|_, w| w.lpms().variant(mode.into()));
 
         scb.set_sleepdeep();
 
         cortex_m::asm::dsb();
         cortex_m::asm::wfi();
+    }
+
+    /// Puts CPU2 into CStop, leaving CPU1 running
+    ///
+    /// Only C2CR1 and CPU2's SLEEPDEEP are written. `mode` is the deepest system low power mode
+    /// CPU2 allows, the system only enters Stop, Standby or Shutdown when **both** CPUs are in
+    /// CStop mode, the resulting mode being the shallowest one requested by the two CPUs. As long
+    /// as CPU1 is running, the system stays in Run mode.
+    ///
+    /// This function returns when CPU2 is woken up.
+    #[cfg(feature = "cm0p")]
+    pub fn c2_enter_cstop(&self, mode: Lpms, scb: &mut SCB) -> Result<(), Error> {
+        self.can_enter(mode)?;
+
+        self.pwr.c2cr1.modify(|_, w| w.lpms().variant(mode.into()));
+
+        scb.set_sleepdeep();
+
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+
+        scb.clear_sleepdeep();
 
         Ok(())
     }
@@ -161,9 +323,7 @@ impl Pwr {
     pub fn enter_low_power_mode_sleeponexit(&self, mode: Lpms, scb: &mut SCB) -> Result<(), Error> {
         let cr1 = &c1_c2!(self.pwr.cr1, self.pwr.c2cr1);
 
-        if self.pwr.cr1.read().lpr().bit_is_set() && mode == Lpms::Stop2 {
-            return Err(Error::LPRunToStop2Illegal);
-        }
+        self.can_enter(mode)?;
 
         cr1.modify(|_, w| w.lpms().variant(mode.into()));
 
@@ -173,6 +333,10 @@ impl Pwr {
         Ok(())
     }
 
+    /// Enter Sleep mode, with the flash in power down (FPDS) according to `flash_powered`
+    ///
+    /// This is only LPSleep if the system is in LPRun, otherwise it's the regular Sleep mode. Use
+    /// [`enter_low_power_sleep`](Self::enter_low_power_sleep) to make sure LPSleep is entered.
     pub fn enter_low_power_sleep_mode(&self, flash_powered: bool, scb: &mut SCB) {
         let cr1 = &c1_c2!(self.pwr.cr1, self.pwr.c2cr1);
 
@@ -184,6 +348,33 @@ impl Pwr {
         cortex_m::asm::wfi();
     }
 
+    /// Enter LPSleep mode
+    ///
+    /// LPSleep is entered from LPRun, see [`enter_low_power_run`](Self::enter_low_power_run).
+    /// Fails with [`Error::NotInLowPowerRun`] otherwise, instead of entering the regular Sleep
+    /// mode. `flash_powerdown` selects whether the flash is powered down during LPSleep, see
+    /// [`set_lp_sleep_flash_powerdown`](Self::set_lp_sleep_flash_powerdown).
+    ///
+    /// This function returns when the CPU is woken up, the system is in LPRun again.
+    pub fn enter_low_power_sleep(
+        &mut self,
+        flash_powerdown: bool,
+        scb: &mut SCB,
+    ) -> Result<(), Error> {
+        if !self.lp_run() {
+            return Err(Error::NotInLowPowerRun);
+        }
+
+        self.set_lp_sleep_flash_powerdown(flash_powerdown);
+
+        scb.clear_sleepdeep();
+
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+
+        Ok(())
+    }
+
     pub fn enter_low_power_sleep_mode_sleeponexit(&self, flash_powered: bool, scb: &mut SCB) {
         let cr1 = &c1_c2!(self.pwr.cr1, self.pwr.c2cr1);
 
@@ -280,6 +471,54 @@ impl Pwr {
         self.pwr.c2cr1.modify(|_, w| w._802ewkup().bit(wkup));
     }
 
+    /// Low-Power mode selection of CPU2 (C2CR1 LPMS)
+    ///
+    /// The CPU2 control registers are shared and can be accessed by CPU1, but the wireless stack
+    /// programs them itself. They are only exposed for reading on CPU1, to coordinate the low
+    /// power modes of both cores.
+    #[cfg(feature = "cm4")]
+    pub fn c2_lp_mode(&self) -> Lpms {
+        self.pwr.c2cr1.read().lpms().bits().try_into().unwrap()
+    }
+
+    /// BLE external wakeup of CPU2 is enabled (C2CR1 BLEEWKUP)
+    #[cfg(feature = "cm4")]
+    pub fn c2_blee_wakeup(&self) -> bool {
+        self.pwr.c2cr1.read().bleewkup().bit()
+    }
+
+    /// 802.15.4 external wakeup of CPU2 is enabled (C2CR1 802EWKUP)
+    #[cfg(feature = "cm4")]
+    pub fn c2_i802e_wakeup(&self) -> bool {
+        self.pwr.c2cr1.read()._802ewkup().bit()
+    }
+
+    /// Wakeup pin WKUPx is enabled for CPU2 (C2CR3)
+    #[cfg(feature = "cm4")]
+    pub fn c2_is_wakeup_src_enabled(&self, src: WakeupSource) -> bool {
+        let c2cr3 = self.pwr.c2cr3.read();
+
+        match src {
+            WakeupSource::Wkup1 => c2cr3.ewup1().bit(),
+            WakeupSource::Wkup2 => c2cr3.ewup2().bit(),
+            WakeupSource::Wkup3 => c2cr3.ewup3().bit(),
+            WakeupSource::Wkup4 => c2cr3.ewup4().bit(),
+            WakeupSource::Wkup5 => c2cr3.ewup5().bit(),
+        }
+    }
+
+    /// BLE host wakeup of CPU2 is enabled (C2CR3 EBLEWUP)
+    #[cfg(feature = "cm4")]
+    pub fn c2_ble_wakeup_src_enabled(&self) -> bool {
+        self.pwr.c2cr3.read().eblewup().bit()
+    }
+
+    /// 802.15.4 host wakeup of CPU2 is enabled (C2CR3 E802WUP)
+    #[cfg(feature = "cm4")]
+    pub fn c2_i802_wakeup_src_enabled(&self) -> bool {
+        self.pwr.c2cr3.read().e802wup().bit()
+    }
+
     /// Disable backup domain write protection\n\n\
     ///
     /// - `false`: Access to RTC and Backup registers disabled
@@ -308,6 +547,23 @@ impl Pwr {
         self.pwr.cr2.modify(|_, w| w.pvme3().bit(en));
     }
 
+    /// Power voltage detector level, `None` if the detector is disabled
+    pub fn power_voltage_detector(&self) -> Option<Pls> {
+        let cr2 = self.pwr.cr2.read();
+
+        cr2.pvde()
+            .bit()
+            .then(|| cr2.pls().bits().try_into().unwrap())
+    }
+
+    pub fn is_peripheral_voltage_monitoring_1(&self) -> bool {
+        self.pwr.cr2.read().pvme1().bit()
+    }
+
+    pub fn is_peripheral_voltage_monitoring_3(&self) -> bool {
+        self.pwr.cr2.read().pvme3().bit()
+    }
+
     /// V_{DDUSB} USB supply valid
     pub fn usb_supply_valid(&self) -> bool {
         self.pwr.cr2.read().usv().bit()
@@ -339,6 +595,22 @@ impl Pwr {
         }
     }
 
+    pub fn is_wakeup_src_enabled(&self, src: WakeupSource) -> bool {
+        let cr3 = c1_c2!(self.pwr.cr3, self.pwr.c2cr3).read();
+
+        match src {
+            WakeupSource::Wkup1 => cr3.ewup1().bit(),
+            WakeupSource::Wkup2 => cr3.ewup2().bit(),
+            WakeupSource::Wkup3 => cr3.ewup3().bit(),
+            WakeupSource::Wkup4 => cr3.ewup4().bit(),
+            WakeupSource::Wkup5 => cr3.ewup5().bit(),
+            #[cfg(feature = "cm0p")]
+            WakeupSource::Ble => cr3.eblewup().bit(),
+            #[cfg(feature = "cm0p")]
+            WakeupSource::_802 => cr3.e802wup().bit(),
+        }
+    }
+
     pub fn wakeup_polarity(&mut self, pin: WakeupSource, polarity: Polarity) {
         match pin {
             WakeupSource::Wkup1 => self
@@ -366,6 +638,61 @@ impl Pwr {
         }
     }
 
+    /// Configures a wakeup pin in one go
+    ///
+    /// Sets the polarity, programs the Standby / Shutdown pull of the pin (PUCRx / PDCRx),
+    /// applies the pull configuration (APC) and enables the wakeup source.
+    ///
+    /// | Source | Pin  |
+    /// |--------|------|
+    /// | WKUP1  | PA0  |
+    /// | WKUP2  | PC13 |
+    /// | WKUP3  | PC12 |
+    /// | WKUP4  | PA2  |
+    /// | WKUP5  | PC5  |
+    pub fn configure_wakeup_pin(&mut self, pin: WakeupSource, polarity: Polarity, pull: Pull) {
+        let (pu, pd) = match pull {
+            Pull::Floating => (false, false),
+            Pull::Up => (true, false),
+            Pull::Down => (false, true),
+        };
+
+        // pucra, pucrb, pdcra, pdcrb have missing fields
+        let pucra = unsafe { &*(&self.pwr.pucra as *const _ as *const stm32wb::Reg<PUCRB_SPEC>) };
+        let pdcra = unsafe { &*(&self.pwr.pdcra as *const _ as *const stm32wb::Reg<PDCRC_SPEC>) };
+        let pucrc = &self.pwr.pucrc;
+        let pdcrc = &self.pwr.pdcrc;
+
+        match pin {
+            WakeupSource::Wkup1 => {
+                pucra.modify(|_, w| w.pu0().bit(pu));
+                pdcra.modify(|_, w| w.pd0().bit(pd));
+            }
+            WakeupSource::Wkup2 => {
+                pucrc.modify(|_, w| w.pu13().bit(pu));
+                pdcrc.modify(|_, w| w.pd13().bit(pd));
+            }
+            WakeupSource::Wkup3 => {
+                pucrc.modify(|_, w| w.pu12().bit(pu));
+                pdcrc.modify(|_, w| w.pd12().bit(pd));
+            }
+            WakeupSource::Wkup4 => {
+                pucra.modify(|_, w| w.pu2().bit(pu));
+                pdcra.modify(|_, w| w.pd2().bit(pd));
+            }
+            WakeupSource::Wkup5 => {
+                pucrc.modify(|_, w| w.pu5().bit(pu));
+                pdcrc.modify(|_, w| w.pd5().bit(pd));
+            }
+            #[cfg(feature = "cm0p")]
+            _ => panic!("Only wakeup pins have a pull configuration"),
+        }
+
+        self.wakeup_polarity(pin, polarity);
+        self.apply_pull_mode_cfg(true);
+        self.enable_wakeup_src(pin);
+    }
+
     pub fn disable_wakeup_src(&mut self, src: WakeupSource) {
         let cr3 = &c1_c2!(self.pwr.cr3, self.pwr.c2cr3);
 
@@ -400,10 +727,20 @@ impl Pwr {
         self.pwr.cr3.modify(|_, w| w.rrs().bit(rrs));
     }
 
+    /// SRAM2a is retained in Standby mode (RRS)
+    pub fn sram2a_retention_enabled(&self) -> bool {
+        self.pwr.cr3.read().rrs().bit()
+    }
+
     pub fn apply_pull_mode_cfg(&mut self, val: bool) {
         c1_c2!(self.pwr.cr3, self.pwr.c2cr3).modify(|_, w| w.apc().bit(val));
     }
 
+    /// The PUCRx / PDCRx pulls are applied in Standby and Shutdown mode (APC)
+    pub fn apply_pull_mode_cfg_enabled(&self) -> bool {
+        c1_c2!(self.pwr.cr3, self.pwr.c2cr3).read().apc().bit()
+    }
+
     #[cfg(feature = "cm4")]
     pub fn listen(&mut self, event: Event) {
         match event {
@@ -428,6 +765,19 @@ impl Pwr {
         }
     }
 
+    #[cfg(feature = "cm4")]
+    pub fn is_listening(&self, event: Event) -> bool {
+        let cr3 = self.pwr.cr3.read();
+
+        match event {
+            Event::BorhSmpsStepDownInBypass => cr3.eborhsdfb().bit(),
+            Event::CriticalRadioPhaseEOA => cr3.ecrpe().bit(),
+            Event::BleEOA => cr3.eblea().bit(),
+            Event::_802EOA => cr3.e802a().bit(),
+            Event::Cpu2Hold => cr3.ec2h().bit(),
+        }
+    }
+
     #[cfg(feature = "cm4")]
     pub fn clear_event_flag(&mut self, event: Event) {
         match event {
@@ -443,6 +793,12 @@ impl Pwr {
         c1_c2!(self.pwr.cr3, self.pwr.c2cr3).modify(|_, w| w.eiwul().bit(en));
     }
 
+    /// Internal wakeup line is enabled (EIWUL)
+    pub fn internal_wakeup_enabled(&self) -> bool {
+        c1_c2!(self.pwr.cr3, self.pwr.c2cr3).read().eiwul().bit()
+    }
+
+    /// Battery charging through a resistor from VDD to VBAT (VBE, VBRS)
     pub fn charge_bat(&mut self, bat: BatteryCharging) {
         match bat {
             BatteryCharging::Disabled => self.pwr.cr4.modify(|_, w| w.vbe().clear_bit()),
@@ -457,11 +813,69 @@ impl Pwr {
         }
     }
 
+    /// Battery charging configuration (VBE, VBRS)
+    ///
+    /// The backup battery voltage is sampled by the ADC on its internal channel 18, which sees
+    /// VBAT / 3 once VBATEN is set in ADC_CCR.
+    pub fn battery_charging(&self) -> BatteryCharging {
+        let cr4 = self.pwr.cr4.read();
+
+        match (cr4.vbe().bit(), cr4.vbrs().bit()) {
+            (false, _) => BatteryCharging::Disabled,
+            (true, true) => BatteryCharging::R1_5,
+            (true, false) => BatteryCharging::R5,
+        }
+    }
+
     /// Boot CPU2 after reset or wakeup from stop or standby modes
     pub fn c2boot(&mut self, val: bool) {
         self.pwr.cr4.modify(|_, w| w.c2boot().bit(val));
     }
 
+    /// CPU2 boot is requested (C2BOOT)
+    pub fn c2boot_enabled(&self) -> bool {
+        self.pwr.cr4.read().c2boot().bit()
+    }
+
+    /// `cpu` is running, i.e. booted and not in Deepsleep mode (EXTSCR C1DS / C2DS)
+    ///
+    /// Note: For CPU2, this only means the core is executing. Wait for the system ready event
+    /// (see [`Sys::wait_ready`](crate::tl_mbox::sys::Sys::wait_ready)) before sending commands.
+    pub fn cpu_running(&self, cpu: Cpu) -> bool {
+        let extscr = self.pwr.extscr.read();
+
+        match cpu {
+            Cpu::C1 => !extscr.c1ds().bit(),
+            Cpu::C2 => self.c2boot_enabled() && !extscr.c2ds().bit(),
+        }
+    }
+
+    /// The current CPU has been in Standby mode (EXTSCR C1SBF / C2SBF)
+    ///
+    /// Check this on startup to tell a wakeup from Standby from a cold boot. The flag is kept
+    /// until cleared with [`clear_standby_stop_flags`](Self::clear_standby_stop_flags).
+    ///
+    /// Note: The STM32WB has no SBF / STOPF in SR1, the flags are reported per CPU in EXTSCR.
+    pub fn standby_flag(&self) -> bool {
+        let extscr = self.pwr.extscr.read();
+
+        c1_c2!(extscr.c1sbf(), extscr.c2sbf()).bit()
+    }
+
+    /// The current CPU has been in Stop mode (EXTSCR C1STOPF / C2STOPF)
+    pub fn stop_flag(&self) -> bool {
+        let extscr = self.pwr.extscr.read();
+
+        c1_c2!(extscr.c1stopf(), extscr.c2stopf()).bit()
+    }
+
+    /// Clears the Standby and Stop flags of the current CPU (EXTSCR C1CSSF / C2CSSF)
+    pub fn clear_standby_stop_flags(&mut self) {
+        self.pwr
+            .extscr
+            .write(|w| c1_c2!(w.c1cssf(), w.c2cssf()).set_bit());
+    }
+
     pub fn smpsvos_factory() -> u8 {
         SmpsVos::get().factory()
     }
@@ -493,16 +907,64 @@ impl Pwr {
         self.pwr.cr5.modify(|_, w| w.sdsc().variant(val));
     }
 
+    /// Current SMPS step-down converter voltage output scaling, see [`smps_vos`](Self::smps_vos)
+    pub fn smps_voltage(&self) -> SmpsVoltage {
+        SmpsVoltage(self.pwr.cr5.read().sdvos().bits())
+    }
+
+    /// Current SMPS startup current selection, see [`smps_sc`](Self::smps_sc)
+    pub fn smps_startup_current(&self) -> SmpsStartupCurrent {
+        self.pwr.cr5.read().sdsc().bits().try_into().unwrap()
+    }
+
     pub fn borh(&mut self, borh: Borh) {
         self.pwr
             .cr5
             .modify(|_, w| w.borhc().bit(borh == Borh::SmpsBypass));
     }
 
+    /// Current action of BORH (BORHC), see [`borh`](Self::borh)
+    pub fn borh_action(&self) -> Borh {
+        if self.pwr.cr5.read().borhc().bit() {
+            Borh::SmpsBypass
+        } else {
+            Borh::SystemReset
+        }
+    }
+
     pub fn smps_enable(&mut self, en: bool) {
         self.pwr.cr5.modify(|_, w| w.sdeb().bit(en));
     }
 
+    /// SMPS step-down converter is enabled (SDEN)
+    pub fn is_smps_enabled(&self) -> bool {
+        self.pwr.cr5.read().sdeb().bit()
+    }
+
+    /// Configures the SMPS for radio operation
+    ///
+    /// With `enabled`, this applies ST's recommended power configuration for BLE, then enables
+    /// the SMPS:
+    ///
+    /// - Output voltage: `vos_step` 50 mV steps below the factory trimmed 1.50 V, ST recommends
+    /// 1.40 V (`vos_step = 2`)
+    /// - Startup current limited to 80 mA
+    /// - BORH forces the SMPS into bypass mode instead of resetting the system, see
+    /// [`Event::BorhSmpsStepDownInBypass`]
+    ///
+    /// Otherwise, the SMPS is put into bypass mode and BORH resets the system again.
+    pub fn configure_smps_for_ble(&mut self, enabled: bool, vos_step: u8) {
+        if enabled {
+            self.smps_vos(Self::smpsvos_factory().saturating_sub(vos_step));
+            self.smps_sc(0);
+            self.borh(Borh::SmpsBypass);
+            self.smps_enable(true);
+        } else {
+            self.smps_enable(false);
+            self.borh(Borh::SystemReset);
+        }
+    }
+
     pub fn sr1(&self) -> sr1::R {
         self.pwr.sr1.read()
     }
@@ -512,10 +974,25 @@ impl Pwr {
     }
 }
 
+/// System power mode, see the module documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerMode {
+    Run { vos: Vos },
+    /// LPRun, the regulator is in low power mode
+    LowPowerRun,
+    Stop0,
+    Stop1,
+    Stop2,
+    Standby,
+    Shutdown,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BatteryCharging {
     Disabled,
+    /// Charging through a 1.5 kOhm resistor
     R1_5,
+    /// Charging through a 5 kOhm resistor
     R5,
 }
 
@@ -575,6 +1052,24 @@ pub enum Lpms {
     Shutdown = 0b100,
 }
 
+/// Stop modes, the low power modes the CPU returns from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopMode {
+    Stop0,
+    Stop1,
+    Stop2,
+}
+
+impl From<StopMode> for Lpms {
+    fn from(mode: StopMode) -> Self {
+        match mode {
+            StopMode::Stop0 => Lpms::Stop0,
+            StopMode::Stop1 => Lpms::Stop1,
+            StopMode::Stop2 => Lpms::Stop2,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum Vos {
@@ -608,6 +1103,48 @@ pub enum Pls {
     PVDIn = 0b111,
 }
 
+/// SMPS step-down converter output voltage (SDVOS), see [`Pwr::smps_voltage`]
+///
+/// The output voltage step size is 50 mV, [`bits`](Self::bits) is the value written by
+/// [`Pwr::smps_vos`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SmpsVoltage(u8);
+
+impl SmpsVoltage {
+    /// Minimum voltage level
+    pub const MIN: Self = Self(0x0);
+    /// Maximum voltage level
+    pub const MAX: Self = Self(0xF);
+
+    /// `None` if `bits` exceeds 0xF
+    pub const fn new(bits: u8) -> Option<Self> {
+        if bits <= Self::MAX.0 {
+            Some(Self(bits))
+        } else {
+            None
+        }
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+/// SMPS step-down converter startup current limit (SDSC), 80 mA + SDSC x 20 mA, see
+/// [`Pwr::smps_startup_current`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum SmpsStartupCurrent {
+    Ma80 = 0,
+    Ma100 = 1,
+    Ma120 = 2,
+    Ma140 = 3,
+    Ma160 = 4,
+    Ma180 = 5,
+    Ma200 = 6,
+    Ma220 = 7,
+}
+
 pub struct SmpsVos(u32);
 
 // See RM0434 Rev 10 p. 181
@@ -3,11 +3,12 @@
 pub(crate) mod rec;
 
 use crate::flash::Latency;
-use crate::pac::{FLASH, PWR, RCC};
+use crate::pac::{CRS, FLASH, PWR, RCC};
 use crate::pwr::Pwr;
 use crate::pwr::Vos;
 use crate::time::Hertz;
 use core::convert::Infallible;
+use core::fmt;
 use fugit::RateExtU32;
 use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
 
@@ -28,6 +29,14 @@ pub enum Error {
     LseDisabled,
     PrescalerNotApplied,
     MsiPllDisabled,
+    HseUndividedVosRange2,
+    /// Backup domain is write protected, see [`Pwr::set_dbp`]
+    BackupDomainProtected,
+    LsiDisabled,
+    /// Only MSI and HSI16 can be used as system clock after a HSE failure
+    IllegalHseFailureFallback,
+    /// The CRS synchronization frequency must be in range of [733 Hz, 48 MHz]
+    CrsSyncIllegalFrequency,
 }
 
 macro_rules! value_error {
@@ -49,7 +58,17 @@ impl RccExt for RCC {
     }
 }
 
-type VcoHertz = fugit::Rate<u32, 1, 3>;
+/// Frequency inside the PLLs, in steps of 1/3 Hz so that MSI and HSE divided by PLLM stay exact
+pub type VcoHertz = fugit::Rate<u32, 1, 3>;
+
+/// Minimum VCO output frequency of the main PLL
+pub const PLL_VCO_MIN: VcoHertz = VcoHertz::MHz(96);
+/// Maximum VCO output frequency of the main PLL
+pub const PLL_VCO_MAX: VcoHertz = VcoHertz::MHz(344);
+/// Minimum VCO output frequency of PLLSAI1
+pub const PLLSAI1_VCO_MIN: VcoHertz = VcoHertz::MHz(64);
+/// Maximum VCO output frequency of PLLSAI1
+pub const PLLSAI1_VCO_MAX: VcoHertz = VcoHertz::MHz(344);
 
 enum PllSrcX {
     Msi(MsiRange),
@@ -85,11 +104,88 @@ pub struct Rcc {
 }
 
 impl Rcc {
+    /// Releases the RCC peripheral for raw register access
+    ///
+    /// The clock configuration is left as is. The peripheral clock handles in `rec` are dropped,
+    /// so this can't be called once one of them has been moved out.
+    pub fn free(self) -> RCC {
+        self.rcc
+    }
+
+    /// Freezes the clock configuration
+    ///
+    /// Waits for ongoing clock switches and prescaler updates, then captures the clock
+    /// frequencies. The returned [`Ccdr`] takes ownership of the RCC and hands out the
+    /// peripheral clock handles, so a driver can be created from a single value:
+    ///
+    /// ```ignore
+    /// let mut ccdr = rcc.freeze();
+    /// let i2c = dp.I2C1.i2c(pins, ccdr.clocks, 100.kHz(), &mut ccdr.peripheral.i2c1);
+    /// ```
+    pub fn freeze(self) -> Ccdr {
+        Ccdr {
+            clocks: self.core_clocks(),
+            peripheral: self.rec,
+            _rcc: self.rcc,
+        }
+    }
+
+    /// Current clock frequencies, waits for ongoing clock switches and prescaler updates
+    fn core_clocks(&self) -> CoreClocks {
+        CoreClocks {
+            sysclk: nb::block!(self.try_sysclk()).unwrap(),
+            hclk1: nb::block!(self.try_hclk1()).unwrap(),
+            hclk2: nb::block!(self.try_hclk2()).unwrap(),
+            hclk4: nb::block!(self.try_hclk4()).unwrap(),
+            pclk1: nb::block!(self.try_pclk1()).unwrap(),
+            pclk2: nb::block!(self.try_pclk2()).unwrap(),
+            i2c1_clk: nb::block!(self.try_i2c1_clk()).unwrap(),
+            i2c3_clk: nb::block!(self.try_i2c3_clk()).unwrap(),
+            lptim1_clk: nb::block!(self.try_lptim1_clk()).unwrap(),
+            lptim2_clk: nb::block!(self.try_lptim2_clk()).unwrap(),
+        }
+    }
+
+    /// Snapshot of the whole clock tree, for logging
+    ///
+    /// Implements `Display` and, with the `defmt` feature, `defmt::Format`, so the configuration
+    /// is printed in a single line:
+    ///
+    /// ```ignore
+    /// defmt::info!("{}", rcc.dump_clock_tree());
+    /// ```
+    pub fn dump_clock_tree(&self) -> ClockTree {
+        ClockTree {
+            clocks: self.core_clocks(),
+            mco: self.mco_frequency(),
+            low_speed: self.low_speed_status(),
+        }
+    }
+
+    /// Gates the clocks of all AHB / APB peripherals of the current core
+    ///
+    /// The flash interface, HSEM, IPCC, SRAM1 and the RTC APB clock stay enabled. `keep` can enable
+    /// further clocks through the `rec` handles, e.g. `|rec| rec.gpioa.enable()`.
+    ///
+    /// This must run before any peripheral is constructed, as clocks of peripherals in use are
+    /// gated as well. Peripheral constructors enable their clock again.
+    pub fn disable_all_peripheral_clocks(&mut self, keep: impl FnOnce(&mut rec::Rec)) {
+        self.rec.disable_all();
+
+        keep(&mut self.rec);
+    }
+
+    /// Enables or disables the MSI (MSION)
+    ///
+    /// Fails with [`Error::ClockInUse`] when disabling the MSI while it drives SYSCLK or the PLL.
+    /// Doesn't wait for MSIRDY.
     pub fn msi_enable(&mut self, en: bool) -> Result<(), Error> {
         if !en && (self.is_sysclk(SysclkSwitch::Msi) || self.is_pllclk(PllSrc::Msi)) {
             return Err(Error::ClockInUse);
         }
 
+        self.rcc.cr.modify(|_, w| w.msion().bit(en));
+
         Ok(())
     }
 
@@ -221,6 +317,28 @@ impl Rcc {
         self.rcc.cr.modify(|_, w| w.hsiasfs().bit(en));
     }
 
+    /// Keeps the HSI16 running as a kernel clock, independent of the system clock (HSIKERON)
+    ///
+    /// Blocks until the HSI16 is ready for the peripherals (HSIKERDY). The HSI16 then stays on in
+    /// Run and Stop modes, even while the system runs on MSI, HSE or the PLL.
+    ///
+    /// Only USART1, LPUART1, I2C1, I2C3, LPTIM1 and LPTIM2 can use the HSI16 as kernel clock,
+    /// select it with [`usart1_clock`](Self::usart1_clock),
+    /// [`lp_uart1_clock`](Self::lp_uart1_clock), [`i2c1_clock`](Self::i2c1_clock),
+    /// [`i2c3_clock`](Self::i2c3_clock), [`lptim1_clock`](Self::lptim1_clock) or
+    /// [`lptim2_clock`](Self::lptim2_clock). This keeps these peripherals clocked in Stop mode, so
+    /// they can wake up the system.
+    pub fn enable_hsi_kernel(&mut self) {
+        self.hsi_ker_enable(true);
+
+        while !self.rcc.cr.read().hsikerdy().bit() {}
+    }
+
+    /// Enables the HSE oscillator (HSEON)
+    ///
+    /// Note: The HSE of the STM32WB55 only supports a 32 MHz crystal, there is no bypass mode for
+    /// an external clock input (RCC_CR bit 18 is reserved, RM0434 Rev 9 p. 275). The crystal is
+    /// trimmed with the load capacitance in HSECR.
     pub fn hse_enable(&mut self, en: bool) {
         self.rcc.cr.modify(|_, w| w.hseon().bit(en));
     }
@@ -229,8 +347,64 @@ impl Rcc {
         self.rcc.cr.modify(|_, w| w.csson().set_bit());
     }
 
-    pub fn hse_divider_enabled(&mut self, div_by_2: bool) {
+    /// A HSE failure was detected by the clock security system (HSECSSF)
+    pub fn hse_css_occurred(&self) -> bool {
+        self.rcc.cifr.read().hsecssf().bit()
+    }
+
+    /// Recovers the system clock after a HSE failure
+    ///
+    /// A failure detected by the HSE CSS raises the NMI, this is meant to be called from the NMI
+    /// handler. The hardware has already switched off the HSE (and a PLL driven by it) and
+    /// switched the system clock to the [`stop_wakeup_clock`](Self::stop_wakeup_clock).
+    ///
+    /// This clears HSECSSF, which would retrigger the NMI otherwise, switches the system clock
+    /// to `fallback` if it's not already running from it and updates the flash latency for the
+    /// new HCLK4. The AHB and APB prescalers aren't changed.
+    ///
+    /// Fails with [`Error::IllegalHseFailureFallback`] if `fallback` isn't MSI or HSI16.
+    pub fn recover_from_hse_failure(&mut self, fallback: SysclkSwitch) -> Result<(), Error> {
+        let (osc, sysclkx) = match fallback {
+            SysclkSwitch::Msi => (PllSrc::Msi, self.sysclkx(fallback)),
+            SysclkSwitch::Hsi16 => (PllSrc::Hsi16, self.sysclkx(fallback)),
+            SysclkSwitch::Hse | SysclkSwitch::Pll => return Err(Error::IllegalHseFailureFallback),
+        };
+
+        self.rcc.cicr.write(|w| w.hsecssc().set_bit());
+
+        let shdpre = self.rcc.extcfgr.read().shdhpre().bits().try_into().unwrap();
+        let current_hclk4 = self.calculate_hclk4(self.current_sysclk(), shdpre);
+        let new_hclk4 = self.calculate_hclk4(self.calculate_sysclk(sysclkx).unwrap(), shdpre);
+
+        if self.current_sysclk_source() != fallback {
+            self.enable_and_wait_osc(osc)?;
+
+            set_flash_latency(current_hclk4.max(new_hclk4));
+
+            self.rcc.cfgr.modify(|_, w| w.sw().variant(fallback.into()));
+            while self.rcc.cfgr.read().sws().bits() != fallback.into() {}
+        }
+
+        set_flash_latency(new_hclk4);
+
+        Ok(())
+    }
+
+    /// Divides HSE by 2 (HSEPRE)
+    ///
+    /// In Range 2, HSE must be divided when used as SYSCLK or PLL input, so disabling
+    /// the divider in that case fails with [`Error::HseUndividedVosRange2`]
+    pub fn hse_divider_enabled(&mut self, pwr: &Pwr, div_by_2: bool) -> Result<(), Error> {
+        if !div_by_2
+            && pwr.power_range() == Vos::Range2
+            && (self.is_sysclk(SysclkSwitch::Hse) || self.is_pllclk(PllSrc::Hse))
+        {
+            return Err(Error::HseUndividedVosRange2);
+        }
+
         self.rcc.cr.modify(|_, w| w.hsepre().bit(div_by_2));
+
+        Ok(())
     }
 
     pub fn pll_enabled(&mut self, _: &Pwr, en: bool) -> nb::Result<(), Error> {
@@ -329,14 +503,7 @@ impl Rcc {
         self.check_sysclk_blocked()?;
 
         let sysclk = |sysclk| {
-            let sysclkx = match sysclk {
-                SysclkSwitch::Msi => SysclkX::Msi(cr.msirange().bits().try_into().unwrap()),
-                SysclkSwitch::Hsi16 => SysclkX::Hsi16,
-                SysclkSwitch::Hse => SysclkX::Hse(cr.hsepre().bit()),
-                SysclkSwitch::Pll => SysclkX::Pll,
-            };
-
-            self.calculate_sysclk(sysclkx)
+            self.calculate_sysclk(self.sysclkx(sysclk))
                 .ok_or(nb::Error::Other(Error::SelectedClockNotEnabled))
         };
 
@@ -345,6 +512,10 @@ impl Rcc {
         let pwr = unsafe { &*PWR::PTR };
         let vos: Vos = pwr.cr1.read().vos().bits().try_into().unwrap();
 
+        if sw == SysclkSwitch::Hse && vos == Vos::Range2 && !cr.hsepre().bit() {
+            return Err(nb::Error::Other(Error::HseUndividedVosRange2));
+        }
+
         self.check_sysclk_rdy(sw)?;
         self.check_sysclk(
             new_sysclk,
@@ -378,19 +549,151 @@ impl Rcc {
         Ok(())
     }
 
+    /// Switches the system clock, enabling the target clock first if necessary
+    ///
+    /// If the target oscillator is off, it's enabled and this function waits until it's ready.
+    /// When switching to the PLL, its input clock and the PLL itself are enabled as well, the
+    /// PLL must already be configured.
+    ///
+    /// This function blocks until the switch is done.
+    pub fn switch_sysclk(&mut self, pwr: &Pwr, sw: SysclkSwitch) -> Result<(), Error> {
+        match sw {
+            SysclkSwitch::Msi => self.enable_and_wait_osc(PllSrc::Msi)?,
+            SysclkSwitch::Hsi16 => self.enable_and_wait_osc(PllSrc::Hsi16)?,
+            SysclkSwitch::Hse => self.enable_and_wait_osc(PllSrc::Hse)?,
+            SysclkSwitch::Pll => {
+                let src = self.pll_source();
+
+                if src == PllSrc::NoClock {
+                    return Err(Error::PllNoClockSelected);
+                }
+
+                self.enable_and_wait_osc(src)?;
+
+                if !self.rcc.cr.read().pllon().bit() {
+                    nb::block!(self.pll_enabled(pwr, true))?;
+                }
+
+                while !self.rcc.cr.read().pllrdy().bit() {}
+            }
+        }
+
+        nb::block!(self.sysclk(pwr, sw))
+    }
+
+    /// Runs `f` with the system clock temporarily switched to `sw`
+    ///
+    /// The switch is done as in [`switch_sysclk`](Self::switch_sysclk). Afterwards, the previous
+    /// system clock and the AHB / APB prescalers are restored, including the flash latency. A PLL
+    /// enabled for the switch is disabled again, oscillators are left running.
+    ///
+    /// `f` must leave the clock tree in a state the previous configuration can be restored from,
+    /// otherwise the restore fails with the corresponding error.
+    pub fn with_sysclk<R>(
+        &mut self,
+        pwr: &Pwr,
+        sw: SysclkSwitch,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Result<R, Error> {
+        let cfgr = self.rcc.cfgr.read();
+        let extcfgr = self.rcc.extcfgr.read();
+
+        let old_sw: SysclkSwitch = cfgr.sws().bits().try_into().unwrap();
+        let hpre = cfgr.hpre().bits().try_into().unwrap();
+        let c2hpre = extcfgr.c2hpre().bits().try_into().unwrap();
+        let shdhpre = extcfgr.shdhpre().bits().try_into().unwrap();
+        let ppre1 = cfgr.ppre1().bits().try_into().unwrap();
+        let ppre2 = cfgr.ppre2().bits().try_into().unwrap();
+        let pll_was_on = self.rcc.cr.read().pllon().bit();
+
+        self.switch_sysclk(pwr, sw)?;
+
+        let r = f(self);
+
+        self.switch_sysclk(pwr, old_sw)?;
+
+        nb::block!(self.hclk1_prescaler(pwr, hpre))?;
+        nb::block!(self.hclk2_prescaler(pwr, c2hpre))?;
+        nb::block!(self.hclk4_prescaler(pwr, shdhpre))?;
+        self.pclk1_prescaler(ppre1);
+        self.pclk2_prescaler(ppre2);
+
+        if !pll_was_on {
+            nb::block!(self.pll_enabled(pwr, false))?;
+        }
+
+        Ok(r)
+    }
+
+    fn enable_and_wait_osc(&mut self, osc: PllSrc) -> Result<(), Error> {
+        let osc = match osc {
+            PllSrc::NoClock => return Ok(()),
+            PllSrc::Msi => Oscillator::Msi,
+            PllSrc::Hsi16 => Oscillator::Hsi16,
+            PllSrc::Hse => Oscillator::Hse,
+        };
+
+        while !self.start_oscillator(osc) {}
+
+        Ok(())
+    }
+
+    /// Enables the oscillator `osc`, returns `Ok` once it's ready
+    ///
+    /// Use `nb::block!` to wait for the oscillator. The LSE is part of the backup domain, fails
+    /// with [`Error::BackupDomainProtected`] if backup domain write access isn't enabled.
+    pub fn enable_oscillator(&mut self, pwr: &Pwr, osc: Oscillator) -> nb::Result<(), Error> {
+        if matches!(osc, Oscillator::Lse) {
+            Self::check_bdp(pwr)?;
+        }
+
+        if self.start_oscillator(osc) {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Sets the enable bit of `osc`, returns whether it's ready
+    fn start_oscillator(&mut self, osc: Oscillator) -> bool {
+        match osc {
+            Oscillator::Msi => {
+                self.rcc.cr.modify(|_, w| w.msion().set_bit());
+                self.rcc.cr.read().msirdy().bit()
+            }
+            Oscillator::Hsi16 => {
+                self.rcc.cr.modify(|_, w| w.hsion().set_bit());
+                self.rcc.cr.read().hsirdy().bit()
+            }
+            Oscillator::Hse => {
+                self.rcc.cr.modify(|_, w| w.hseon().set_bit());
+                self.rcc.cr.read().hserdy().bit()
+            }
+            Oscillator::Hsi48 => {
+                self.rcc.crrcr.modify(|_, w| w.hsi48on().set_bit());
+                self.rcc.crrcr.read().hsi48rdy().bit()
+            }
+            Oscillator::Lsi1 => {
+                self.rcc.csr.modify(|_, w| w.lsi1on().set_bit());
+                self.rcc.csr.read().lsi1rdy().bit()
+            }
+            Oscillator::Lsi2 => {
+                self.rcc.csr.modify(|_, w| w.lsi2on().set_bit());
+                self.rcc.csr.read().lsi2rdy().bit()
+            }
+            Oscillator::Lse => {
+                self.rcc.bdcr.modify(|_, w| w.lseon().set_bit());
+                self.rcc.bdcr.read().lserdy().bit()
+            }
+        }
+    }
+
     pub fn hclk1_prescaler(&mut self, _: &Pwr, scale: PreScaler) -> nb::Result<(), Error> {
-        let cr = self.rcc.cr.read();
         let cfgr = self.rcc.cfgr.read();
 
         self.check_sysclk_blocked()?;
 
-        let sw: SysclkSwitch = cfgr.sw().bits().try_into().unwrap();
-        let sysclkx = match sw {
-            SysclkSwitch::Msi => SysclkX::Msi(cr.msirange().bits().try_into().unwrap()),
-            SysclkSwitch::Hsi16 => SysclkX::Hsi16,
-            SysclkSwitch::Hse => SysclkX::Hse(cr.hsepre().bit()),
-            SysclkSwitch::Pll => SysclkX::Pll,
-        };
+        let sysclkx = self.sysclkx(cfgr.sw().bits().try_into().unwrap());
 
         let pwr = unsafe { &*PWR::PTR };
         let vos: Vos = pwr.cr1.read().vos().bits().try_into().unwrap();
@@ -413,18 +716,11 @@ impl Rcc {
     }
 
     pub fn hclk2_prescaler(&mut self, _: &Pwr, scale: PreScaler) -> nb::Result<(), Error> {
-        let cr = self.rcc.cr.read();
         let cfgr = self.rcc.cfgr.read();
 
         self.check_sysclk_blocked()?;
 
-        let sw: SysclkSwitch = cfgr.sw().bits().try_into().unwrap();
-        let sysclkx = match sw {
-            SysclkSwitch::Msi => SysclkX::Msi(cr.msirange().bits().try_into().unwrap()),
-            SysclkSwitch::Hsi16 => SysclkX::Hsi16,
-            SysclkSwitch::Hse => SysclkX::Hse(cr.hsepre().bit()),
-            SysclkSwitch::Pll => SysclkX::Pll,
-        };
+        let sysclkx = self.sysclkx(cfgr.sw().bits().try_into().unwrap());
 
         let pwr = unsafe { &*PWR::PTR };
         let vos: Vos = pwr.cr1.read().vos().bits().try_into().unwrap();
@@ -449,18 +745,11 @@ impl Rcc {
     }
 
     pub fn hclk4_prescaler(&mut self, _: &Pwr, scale: PreScaler) -> nb::Result<(), Error> {
-        let cr = self.rcc.cr.read();
         let cfgr = self.rcc.cfgr.read();
 
         self.check_sysclk_blocked()?;
 
-        let sw: SysclkSwitch = cfgr.sw().bits().try_into().unwrap();
-        let sysclkx = match sw {
-            SysclkSwitch::Msi => SysclkX::Msi(cr.msirange().bits().try_into().unwrap()),
-            SysclkSwitch::Hsi16 => SysclkX::Hsi16,
-            SysclkSwitch::Hse => SysclkX::Hse(cr.hsepre().bit()),
-            SysclkSwitch::Pll => SysclkX::Pll,
-        };
+        let sysclkx = self.sysclkx(cfgr.sw().bits().try_into().unwrap());
 
         let pwr = unsafe { &*PWR::PTR };
         let vos: Vos = pwr.cr1.read().vos().bits().try_into().unwrap();
@@ -491,7 +780,7 @@ impl Rcc {
 
         self.rcc
             .extcfgr
-            .modify(|_, w| w.c2hpre().variant(scale.into()));
+            .modify(|_, w| w.shdhpre().variant(scale.into()));
 
         if scale > current_scale {
             while self.rcc.extcfgr.read().shdhpref().bit_is_clear() {}
@@ -502,6 +791,17 @@ impl Rcc {
         Ok(())
     }
 
+    /// Current CPU2 HCLK2 prescaler (C2HPRE), see [`hclk2_prescaler`](Self::hclk2_prescaler)
+    pub fn hclk2_prescaler_get(&self) -> PreScaler {
+        self.rcc.extcfgr.read().c2hpre().bits().try_into().unwrap()
+    }
+
+    /// Current HCLK4 prescaler of the flash, SRAM2 and shared peripherals (EXTCFGR SHDHPRE),
+    /// see [`hclk4_prescaler`](Self::hclk4_prescaler)
+    pub fn hclk4_prescaler_get(&self) -> PreScaler {
+        self.rcc.extcfgr.read().shdhpre().bits().try_into().unwrap()
+    }
+
     pub fn pclk1_prescaler(&mut self, scale: PpreScaler) {
         self.rcc.cfgr.modify(|_, w| w.ppre1().variant(scale.into()));
     }
@@ -510,6 +810,63 @@ impl Rcc {
         self.rcc.cfgr.modify(|_, w| w.ppre2().variant(scale.into()));
     }
 
+    /// HCLK1 resulting from prescaler `hpre`, computed from the current sysclk
+    ///
+    /// No register is written, the frequency can be checked before calling
+    /// [`hclk1_prescaler`](Self::hclk1_prescaler).
+    pub fn predict_hclk1(&self, hpre: PreScaler) -> Hertz {
+        self.calculate_hclk1(self.current_sysclk(), hpre)
+    }
+
+    /// HCLK2 resulting from prescaler `c2hpre`, computed from the current sysclk
+    pub fn predict_hclk2(&self, c2hpre: PreScaler) -> Hertz {
+        self.calculate_hclk2(self.current_sysclk(), c2hpre)
+    }
+
+    /// HCLK4 resulting from prescaler `shdpre`, computed from the current sysclk
+    pub fn predict_hclk4(&self, shdpre: PreScaler) -> Hertz {
+        self.calculate_hclk4(self.current_sysclk(), shdpre)
+    }
+
+    /// PCLK1 resulting from prescalers `hpre` and `ppre1`, computed from the current sysclk
+    pub fn predict_pclk1(&self, hpre: PreScaler, ppre1: PpreScaler) -> Hertz {
+        self.calculate_pclk1(self.predict_hclk1(hpre), ppre1)
+    }
+
+    /// PCLK2 resulting from prescalers `hpre` and `ppre2`, computed from the current sysclk
+    pub fn predict_pclk2(&self, hpre: PreScaler, ppre2: PpreScaler) -> Hertz {
+        self.calculate_pclk2(self.predict_hclk1(hpre), ppre2)
+    }
+
+    /// Flash latency required for prescaler `shdpre` in the current voltage range
+    pub fn predict_flash_latency(&self, shdpre: PreScaler) -> Latency {
+        let pwr = unsafe { &*PWR::PTR };
+        let vos: Vos = pwr.cr1.read().vos().bits().try_into().unwrap();
+
+        Latency::from(vos, self.predict_hclk4(shdpre))
+    }
+
+    /// Frequency of the clock currently driving the system clock (SWS)
+    fn current_sysclk(&self) -> Hertz {
+        // The PLL can't be sysclk without a source
+        self.calculate_sysclk(self.sysclkx(self.current_sysclk_source()))
+            .unwrap()
+    }
+
+    /// Clock behind the system clock switch value `sw`, with the current MSI range and HSE
+    /// prescaler
+    fn sysclkx(&self, sw: SysclkSwitch) -> SysclkX {
+        let cr = self.rcc.cr.read();
+
+        match sw {
+            SysclkSwitch::Msi => SysclkX::Msi(cr.msirange().bits().try_into().unwrap()),
+            SysclkSwitch::Hsi16 => SysclkX::Hsi16,
+            SysclkSwitch::Hse => SysclkX::Hse(cr.hsepre().bit()),
+            SysclkSwitch::Pll => SysclkX::Pll,
+        }
+    }
+
+    /// Clock selected for the RF system (RFCSS)
     pub fn rf_clock(&self) -> RfClock {
         if self.rcc.extcfgr.read().rfcss().bit() {
             RfClock::Hse
@@ -518,10 +875,45 @@ impl Rcc {
         }
     }
 
+    /// The clock selected by [`rf_clock`](Self::rf_clock) is enabled and ready
+    ///
+    /// The BLE stack on CPU2 requires a stable HSE32 as RF clock, check this before starting it.
+    pub fn rf_clock_ready(&self) -> bool {
+        let cr = self.rcc.cr.read();
+
+        match self.rf_clock() {
+            RfClock::Hsi16 => cr.hsion().bit() && cr.hsirdy().bit(),
+            RfClock::Hse => cr.hseon().bit() && cr.hserdy().bit(),
+        }
+    }
+
+    /// Clock currently used as system clock (SWS)
+    ///
+    /// During a switch, this is still the old clock until the hardware has completed it
+    pub fn current_sysclk_source(&self) -> SysclkSwitch {
+        self.rcc.cfgr.read().sws().bits().try_into().unwrap()
+    }
+
+    /// The main PLL is currently driving the system clock
+    pub fn is_pll_system_clock(&self) -> bool {
+        self.current_sysclk_source() == SysclkSwitch::Pll
+    }
+
+    /// Input clock of the main PLL and PLLSAI1
+    pub fn pll_source(&self) -> PllSrc {
+        self.rcc.pllcfgr.read().pllsrc().bits().try_into().unwrap()
+    }
+
+    /// Clock used as system clock when waking up from Stop mode or on HSE CSS failure
     pub fn stop_css_wakeup_clock(&mut self, clk: Stopwuck) {
         self.rcc
             .cfgr
-            .modify(|_, w| w.stopwuck().variant(clk == Stopwuck::Hsi16));
+            .modify(|_, w| w.stopwuck().variant(clk.bit()));
+    }
+
+    /// Clock used as system clock when waking up from Stop mode or on HSE CSS failure (STOPWUCK)
+    pub fn stop_wakeup_clock(&self) -> Stopwuck {
+        Stopwuck::from_bit(self.rcc.cfgr.read().stopwuck().bit())
     }
 
     pub fn mco(&mut self, clk: McoSelector, scale: McoPrescaler) {
@@ -531,6 +923,105 @@ impl Rcc {
         self.rcc.cfgr.modify(|_, w| w.mcosel().variant(clk.into()));
     }
 
+    /// Like [`mco`](Self::mco), but checks that the selected clock is enabled and ready first
+    ///
+    /// Fails with [`Error::SelectedClockNotEnabled`] otherwise, MCOSEL isn't written in this case
+    pub fn mco_checked(&mut self, clk: McoSelector, scale: McoPrescaler) -> Result<(), Error> {
+        if !self.mco_source_ready(clk) {
+            return Err(Error::SelectedClockNotEnabled);
+        }
+
+        self.mco(clk, scale);
+
+        Ok(())
+    }
+
+    fn mco_source_ready(&self, clk: McoSelector) -> bool {
+        let cr = self.rcc.cr.read();
+
+        match clk {
+            McoSelector::Disabled | McoSelector::Sysclk => true,
+            McoSelector::Msi => cr.msirdy().bit(),
+            McoSelector::Hsi16 => cr.hsirdy().bit(),
+            McoSelector::HseAfter => cr.hserdy().bit(),
+            McoSelector::HseBefore => cr.hseon().bit(),
+            McoSelector::Pllrclk => cr.pllrdy().bit() && self.rcc.pllcfgr.read().pllren().bit(),
+            McoSelector::Lsi1 => self.rcc.csr.read().lsi1rdy().bit(),
+            McoSelector::Lsi2 => self.rcc.csr.read().lsi2rdy().bit(),
+            McoSelector::Lse => self.rcc.bdcr.read().lserdy().bit(),
+            McoSelector::Hsi48 => self.rcc.crrcr.read().hsi48rdy().bit(),
+        }
+    }
+
+    /// Frequency of the clock currently output on MCO
+    ///
+    /// Returns `None` if MCO is disabled or the selected source isn't running
+    pub fn mco_frequency(&self) -> Option<Hertz> {
+        let cr = self.rcc.cr.read();
+        let cfgr = self.rcc.cfgr.read();
+
+        let clk: McoSelector = cfgr.mcosel().bits().try_into().ok()?;
+        let scale: McoPrescaler = cfgr.mcopre().bits().try_into().ok()?;
+
+        let freq = match clk {
+            McoSelector::Disabled => return None,
+            McoSelector::Sysclk => {
+                self.calculate_sysclk(self.sysclkx(self.current_sysclk_source()))?
+            }
+            _ if !self.mco_source_ready(clk) => return None,
+            McoSelector::Msi => MsiRange::try_from(cr.msirange().bits()).unwrap().hertz(),
+            McoSelector::Hsi16 => hsi16_hertz(),
+            McoSelector::HseAfter | McoSelector::HseBefore => hse_hertz(),
+            McoSelector::Pllrclk => self.calculate_sysclk(SysclkX::Pll)?,
+            McoSelector::Lsi1 => lsi1_hertz(),
+            McoSelector::Lsi2 => lsi2_hertz(),
+            McoSelector::Lse => lse_hertz(),
+            McoSelector::Hsi48 => hsi48_hertz(),
+        };
+
+        Some(freq / scale.div_scale() as u32)
+    }
+
+    /// Validates and writes the whole main PLL configuration
+    ///
+    /// The VCO input and output and the PLL outputs are checked against the current voltage
+    /// range first, nothing is written on an error. The source and M divider are shared with the
+    /// PLLSAI1, so both PLLs must be off.
+    pub fn configure_pll(&mut self, pwr: &Pwr, cfg: PllConfig) -> Result<(), Error> {
+        let cr = self.rcc.cr.read();
+
+        if cr.pllon().bit() || cr.pllsai1on().bit() {
+            return Err(Error::PllEnabled);
+        }
+
+        let pllsrcx = match cfg.src {
+            PllSrc::NoClock => return Err(Error::PllNoClockSelected),
+            PllSrc::Msi => PllSrcX::Msi(cr.msirange().bits().try_into().unwrap()),
+            PllSrc::Hsi16 => PllSrcX::Hsi16,
+            PllSrc::Hse => PllSrcX::Hse(cr.hsepre().bit()),
+        };
+
+        let vco_in = Self::pll_m_checked(pllsrcx, pwr.power_range(), cfg.m)?;
+        Self::check_pll(vco_in, cfg.n, cfg.p, cfg.q, cfg.r)?;
+
+        self.rcc.pllcfgr.modify(|_, w| {
+            w.pllsrc()
+                .variant(cfg.src.into())
+                .pllm()
+                .variant(cfg.m.into())
+                .plln()
+                .variant(cfg.n.into())
+                .pllp()
+                .variant(cfg.p.into())
+                .pllq()
+                .variant(cfg.q.into())
+                .pllr()
+                .variant(cfg.r.into())
+        });
+
+        Ok(())
+    }
+
     pub fn pll_src(&mut self, src: PllSrc) -> Result<(), Error> {
         if self.rcc.cr.read().pllon().bit() || self.rcc.cr.read().pllsai1on().bit() {
             return Err(Error::PllEnabled);
@@ -675,6 +1166,78 @@ impl Rcc {
         self.rcc.pllsai1cfgr.modify(|_, w| w.pllren().bit(en));
     }
 
+    /// PLLSAI1PCLK output is enabled (PLLPEN)
+    pub fn pllsai1p_enabled(&self) -> bool {
+        self.rcc.pllsai1cfgr.read().pllpen().bit()
+    }
+
+    /// PLLSAI1QCLK output is enabled (PLLQEN)
+    pub fn pllsai1q_enabled(&self) -> bool {
+        self.rcc.pllsai1cfgr.read().pllqen().bit()
+    }
+
+    /// PLLSAI1RCLK output is enabled (PLLREN)
+    pub fn pllsai1r_enabled(&self) -> bool {
+        self.rcc.pllsai1cfgr.read().pllren().bit()
+    }
+
+    /// Frequency of PLLSAI1PCLK, `None` if PLLSAI1 isn't locked or the output is disabled
+    pub fn pllsai1p_frequency(&self) -> Option<Hertz> {
+        if !self.pllsai1p_enabled() {
+            return None;
+        }
+
+        let pllp: Pllp = self.rcc.pllsai1cfgr.read().pllp().bits().try_into().unwrap();
+
+        self.pllsai1_vco()
+            .map(|vco| (vco / pllp.get() as u32).convert())
+    }
+
+    /// Frequency of PLLSAI1QCLK, `None` if PLLSAI1 isn't locked or the output is disabled
+    pub fn pllsai1q_frequency(&self) -> Option<Hertz> {
+        if !self.pllsai1q_enabled() {
+            return None;
+        }
+
+        let pllq: PllQR = self.rcc.pllsai1cfgr.read().pllq().bits().try_into().unwrap();
+
+        self.pllsai1_vco()
+            .map(|vco| (vco / pllq.div_factor() as u32).convert())
+    }
+
+    /// Frequency of PLLSAI1RCLK, `None` if PLLSAI1 isn't locked or the output is disabled
+    pub fn pllsai1r_frequency(&self) -> Option<Hertz> {
+        if !self.pllsai1r_enabled() {
+            return None;
+        }
+
+        let pllr: PllQR = self.rcc.pllsai1cfgr.read().pllr().bits().try_into().unwrap();
+
+        self.pllsai1_vco()
+            .map(|vco| (vco / pllr.div_factor() as u32).convert())
+    }
+
+    /// PLLSAI1 VCO output frequency, `None` unless PLLSAI1 is locked
+    fn pllsai1_vco(&self) -> Option<VcoHertz> {
+        let cr = self.rcc.cr.read();
+
+        if !cr.pllsai1rdy().bit() {
+            return None;
+        }
+
+        let pllcfgr = self.rcc.pllcfgr.read();
+        let pllsrcx = match self.pll_source() {
+            PllSrc::NoClock => return None,
+            PllSrc::Msi => PllSrcX::Msi(cr.msirange().bits().try_into().unwrap()),
+            PllSrc::Hsi16 => PllSrcX::Hsi16,
+            PllSrc::Hse => PllSrcX::Hse(cr.hsepre().bit()),
+        };
+        let pllm = pllcfgr.pllm().bits().try_into().unwrap();
+        let plln: Pllsai1N = self.rcc.pllsai1cfgr.read().plln().bits().try_into().unwrap();
+
+        Some(Self::pll_m(pllsrcx, pllm) * plln.get() as u32)
+    }
+
     pub fn usart1_clock(&mut self, clock: Usart1sel) {
         self.rcc
             .ccipr
@@ -699,42 +1262,259 @@ impl Rcc {
             .modify(|_, w| w.i2c3sel().variant(clock.into()));
     }
 
+    /// Selects the SMPS step-down converter clock and its prescaler
+    ///
+    /// With [`Smpssel::Msi`], the current MSI range is checked against the SMPS constraints: it
+    /// must be [compatible](MsiRange::smps_compatible), and 24 MHz can't be divided down to
+    /// [`Smpsdiv::S8MHz`].
+    pub fn smps_clock(&mut self, clock: Smpssel, div: Smpsdiv) -> Result<(), Error> {
+        if clock == Smpssel::Msi {
+            let range: MsiRange = self.rcc.cr.read().msirange().bits().try_into().unwrap();
+
+            if !range.smps_compatible() {
+                return Err(Error::SmpsMsiUnsupportedRange);
+            }
+
+            if range == MsiRange::R24M && div == Smpsdiv::S8MHz {
+                return Err(Error::SmpsMsi24MhzTo4MhzIllegal);
+            }
+        }
+
+        self.rcc.smpscr.modify(|_, w| {
+            w.smpssel()
+                .variant(clock.into())
+                .smpsdiv()
+                .variant(div.into())
+        });
+
+        Ok(())
+    }
+
     pub fn lptim1_clock(&mut self, clock: LptimSel) {
         self.rcc
             .ccipr
             .modify(|_, w| w.lptim1sel().variant(clock.into()));
     }
 
-    pub fn lptim2_clock(&mut self, clock: LptimSel) {
-        self.rcc
-            .ccipr
-            .modify(|_, w| w.lptim2sel().variant(clock.into()));
-    }
+    pub fn lptim2_clock(&mut self, clock: LptimSel) {
+        self.rcc
+            .ccipr
+            .modify(|_, w| w.lptim2sel().variant(clock.into()));
+    }
+
+    pub fn sai1_clock(&mut self, clock: Sai1Sel) {
+        self.rcc
+            .ccipr
+            .modify(|_, w| w.sai1sel().variant(clock.into()));
+    }
+
+    /// Note: This doesn't check if speed of selected clock is indeed 48 MHz. Initialization
+    /// of peripherals requiring this exact speed will fail if that requirement isn't fulfilled
+    pub fn clock_48(&mut self, clock: Clk48Sel) {
+        self.rcc
+            .ccipr
+            .modify(|_, w| w.clk48sel().variant(clock.into()));
+    }
+
+    pub fn adc_clock(&mut self, clock: AdcSel) {
+        self.rcc
+            .ccipr
+            .modify(|_, w| w.adcsel().variant(clock.into()));
+    }
+
+    pub fn rng_clock(&mut self, clock: RngSel) {
+        self.rcc
+            .ccipr
+            .modify(|_, w| w.rngsel().variant(clock.into()));
+    }
+
+    /// Runs CLK48 from HSI48, trimmed by the clock recovery system (CRS)
+    ///
+    /// Enables HSI48 and waits until it's ready, selects it as CLK48 source, enables the CRS
+    /// and configures automatic trimming against `sync`.
+    ///
+    /// Note: When synchronizing to the USB SOF, the USB peripheral must be enabled for the
+    /// trimming to take effect.
+    ///
+    /// Fails with [`Error::CrsSyncIllegalFrequency`] if the frequency of `sync` doesn't fit the
+    /// CRS reload counter, nothing is changed in this case.
+    pub fn use_hsi48_with_crs(
+        &mut self,
+        crs: &mut rec::CRS,
+        sync: CrsSyncSource,
+    ) -> Result<(), Error> {
+        let (reload, felim) = crs_reload_felim(hsi48_hertz(), sync.hertz())?;
+
+        self.rcc.crrcr.modify(|_, w| w.hsi48on().set_bit());
+        while !self.rcc.crrcr.read().hsi48rdy().bit() {}
+
+        self.clock_48(Clk48Sel::Hsi48);
+
+        crs.enable();
+        crs.reset();
+
+        // SAFETY: Access to CRS is guarded by the REC token
+        let regs = unsafe { &*CRS::PTR };
+
+        regs.cfgr.modify(|_, w| {
+            w.syncsrc()
+                .variant(sync.syncsrc())
+                .reload()
+                .variant(reload)
+                .felim()
+                .variant(felim)
+        });
+        regs.cr.modify(|_, w| w.autotrimen().set_bit().cen().set_bit());
+
+        Ok(())
+    }
+
+    /// Enables the LSE oscillator
+    ///
+    /// The LSE is part of the backup domain, fails with [`Error::BackupDomainProtected`]
+    /// if backup domain write access isn't enabled
+    pub fn lse_enable(&mut self, pwr: &Pwr, en: bool) -> Result<(), Error> {
+        Self::check_bdp(pwr)?;
+
+        if !en && self.rcc.cr.read().msipllen().bit() {
+            return Err(Error::ClockInUse);
+        }
+
+        self.rcc.bdcr.modify(|_, w| w.lseon().bit(en));
+
+        Ok(())
+    }
+
+    /// Enables the LSE clock security system (LSECSS)
+    ///
+    /// LSE and LSI1 or LSI2 must be enabled and ready, and the RTC clock should already be
+    /// selected. Once enabled, LSECSS can only be disabled by a backup domain reset.
+    pub fn enable_lse_css(&mut self, pwr: &Pwr) -> Result<(), Error> {
+        Self::check_bdp(pwr)?;
+
+        let bdcr = self.rcc.bdcr.read();
+        let csr = self.rcc.csr.read();
+
+        if !bdcr.lseon().bit() || !bdcr.lserdy().bit() {
+            return Err(Error::LseDisabled);
+        }
+
+        if !csr.lsi1rdy().bit() && !csr.lsi2rdy().bit() {
+            return Err(Error::LsiDisabled);
+        }
+
+        self.rcc.bdcr.modify(|_, w| w.lsecsson().set_bit());
+
+        Ok(())
+    }
+
+    /// A failure on the LSE oscillator was detected (LSECSSD)
+    pub fn lse_css_failed(&self) -> bool {
+        self.rcc.bdcr.read().lsecssd().bit()
+    }
+
+    /// Snapshot of the low speed oscillators (LSE, LSI1, LSI2) and the RTC / LSCO clock selection
+    ///
+    /// Reading doesn't require backup domain write access.
+    pub fn low_speed_status(&self) -> LowSpeedStatus {
+        let bdcr = self.rcc.bdcr.read();
+        let csr = self.rcc.csr.read();
+
+        LowSpeedStatus {
+            lse_on: bdcr.lseon().bit(),
+            lse_ready: bdcr.lserdy().bit(),
+            lse_bypass: bdcr.lsebyp().bit(),
+            lse_css_failed: bdcr.lsecssd().bit(),
+            lsi1_on: csr.lsi1on().bit(),
+            lsi1_ready: csr.lsi1rdy().bit(),
+            lsi2_on: csr.lsi2on().bit(),
+            lsi2_ready: csr.lsi2rdy().bit(),
+            rtc_clock: bdcr.rtcsel().bits().try_into().unwrap(),
+            lsco: bdcr.lscoen().bit().then(|| {
+                if bdcr.lscosel().bit() {
+                    LscoSel::Lse
+                } else {
+                    LscoSel::Lsi
+                }
+            }),
+        }
+    }
+
+    /// LSE oscillator drive capability
+    pub fn lse_drive(&mut self, pwr: &Pwr, drive: LseDrive) -> Result<(), Error> {
+        Self::check_bdp(pwr)?;
+
+        self.rcc
+            .bdcr
+            .modify(|_, w| w.lsedrv().variant(drive.into()));
+
+        Ok(())
+    }
+
+    /// RTC clock source
+    ///
+    /// Note: Once selected, the RTC clock source can't be changed anymore until the next
+    /// backup domain reset
+    pub fn rtc_clock(&mut self, pwr: &Pwr, clock: RtcSel) -> Result<(), Error> {
+        Self::check_bdp(pwr)?;
+
+        self.rcc
+            .bdcr
+            .modify(|_, w| w.rtcsel().variant(clock.into()));
+
+        Ok(())
+    }
+
+    pub fn rtc_enable(&mut self, pwr: &Pwr, en: bool) -> Result<(), Error> {
+        Self::check_bdp(pwr)?;
+
+        self.rcc.bdcr.modify(|_, w| w.rtcen().bit(en));
+
+        Ok(())
+    }
+
+    /// Resets the backup domain (BDRST)
+    ///
+    /// This stops the LSE and the RTC and clears the RTC clock selection, which is the only way to
+    /// change the RTC clock source once it's been set. Backup domain write access is enabled for
+    /// the sequence and restored afterwards.
+    ///
+    /// Fails with [`Error::ClockInUse`] if the LSE is used by the MSI PLL-mode, and with
+    /// [`Error::BackupDomainProtected`] if the reset didn't take effect.
+    pub fn reset_backup_domain(&mut self, pwr: &mut Pwr) -> Result<(), Error> {
+        if self.rcc.cr.read().msipllen().bit() {
+            return Err(Error::ClockInUse);
+        }
+
+        let dbp = pwr.dbp();
+
+        pwr.set_dbp(true);
+        while !pwr.dbp() {}
+
+        self.rcc.bdcr.modify(|_, w| w.bdrst().set_bit());
+        self.rcc.bdcr.modify(|_, w| w.bdrst().clear_bit());
+
+        let bdcr = self.rcc.bdcr.read();
+        let res = if bdcr.rtcsel().bits() != RtcSel::NoClock.into() || bdcr.lseon().bit() {
+            Err(Error::BackupDomainProtected)
+        } else {
+            Ok(())
+        };
+
+        pwr.set_dbp(dbp);
 
-    pub fn sai1_clock(&mut self, clock: Sai1Sel) {
-        self.rcc
-            .ccipr
-            .modify(|_, w| w.sai1sel().variant(clock.into()));
+        res
     }
 
-    /// Note: This doesn't check if speed of selected clock is indeed 48 MHz. Initialization
-    /// of peripherals requiring this exact speed will fail if that requirement isn't fulfilled
-    pub fn clock_48(&mut self, clock: Clk48Sel) {
-        self.rcc
-            .ccipr
-            .modify(|_, w| w.clk48sel().variant(clock.into()));
-    }
+    /// Low speed clock output (LSCO)
+    pub fn lsco(&mut self, pwr: &Pwr, clock: LscoSel, en: bool) -> Result<(), Error> {
+        Self::check_bdp(pwr)?;
 
-    pub fn adc_clock(&mut self, clock: AdcSel) {
         self.rcc
-            .ccipr
-            .modify(|_, w| w.adcsel().variant(clock.into()));
-    }
+            .bdcr
+            .modify(|_, w| w.lscosel().bit(clock == LscoSel::Lse).lscoen().bit(en));
 
-    pub fn rng_clock(&mut self, clock: RngSel) {
-        self.rcc
-            .ccipr
-            .modify(|_, w| w.rngsel().variant(clock.into()));
+        Ok(())
     }
 
     pub fn listen(&mut self, event: Event, listen: bool) {
@@ -769,6 +1549,14 @@ impl Rcc {
         });
     }
 
+    fn check_bdp(pwr: &Pwr) -> Result<(), Error> {
+        if !pwr.dbp() {
+            return Err(Error::BackupDomainProtected);
+        }
+
+        Ok(())
+    }
+
     fn check_sysclk_blocked(&self) -> nb::Result<(), Error> {
         if self.sysclk_blocked() {
             Err(nb::Error::WouldBlock)
@@ -795,7 +1583,10 @@ impl Rcc {
         };
 
         if vos == Vos::Range2 && pll_m_in > 16.MHz::<1, 1>() {
-            return Err(Error::PllClkIllegalRange);
+            return match src {
+                PllSrcX::Hse(false) => Err(Error::HseUndividedVosRange2),
+                _ => Err(Error::PllClkIllegalRange),
+            };
         }
 
         let vco_in = pll_m_in.convert() / pllm.div_factor() as u32;
@@ -822,7 +1613,7 @@ impl Rcc {
             .raw()
             .checked_mul(plln.get() as u32)
             .map(VcoHertz::from_raw)
-            .filter(|&v| v >= VcoHertz::MHz(96) && v <= VcoHertz::MHz(344))
+            .filter(|&v| v >= PLL_VCO_MIN && v <= PLL_VCO_MAX)
             .ok_or(Error::PllClkIllegalRange)
     }
 
@@ -855,7 +1646,7 @@ impl Rcc {
             .raw()
             .checked_mul(plln.get() as u32)
             .map(VcoHertz::from_raw)
-            .filter(|&v| v >= VcoHertz::MHz(64) && v <= VcoHertz::MHz(344))
+            .filter(|&v| v >= PLLSAI1_VCO_MIN && v <= PLLSAI1_VCO_MAX)
             .ok_or(Error::PllClkIllegalRange)
     }
 
@@ -1066,6 +1857,8 @@ pub trait TryClocks {
     fn try_pclk2(&self) -> nb::Result<Hertz, Infallible>;
     fn try_i2c1_clk(&self) -> nb::Result<Option<Hertz>, Infallible>;
     fn try_i2c3_clk(&self) -> nb::Result<Option<Hertz>, Infallible>;
+    fn try_lptim1_clk(&self) -> nb::Result<Option<Hertz>, Infallible>;
+    fn try_lptim2_clk(&self) -> nb::Result<Option<Hertz>, Infallible>;
 }
 
 impl TryClocks for Rcc {
@@ -1075,15 +1868,7 @@ impl TryClocks for Rcc {
             return Err(nb::Error::WouldBlock);
         }
 
-        let sysclk: SysclkSwitch = cfgr.sw().bits().try_into().unwrap();
-        let sysclkx = match sysclk {
-            SysclkSwitch::Msi => {
-                SysclkX::Msi(self.rcc.cr.read().msirange().bits().try_into().unwrap())
-            }
-            SysclkSwitch::Hsi16 => SysclkX::Hsi16,
-            SysclkSwitch::Hse => SysclkX::Hse(self.rcc.cr.read().hsepre().bit()),
-            SysclkSwitch::Pll => SysclkX::Pll,
-        };
+        let sysclkx = self.sysclkx(cfgr.sw().bits().try_into().unwrap());
 
         Ok(self.calculate_sysclk(sysclkx).unwrap())
     }
@@ -1164,6 +1949,49 @@ impl TryClocks for Rcc {
             }
         }
     }
+
+    fn try_lptim1_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
+        let lptim_clk = self.rcc.ccipr.read().lptim1sel().bits().try_into().unwrap();
+        self.try_lptim_clk(lptim_clk)
+    }
+
+    fn try_lptim2_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
+        let lptim_clk = self.rcc.ccipr.read().lptim2sel().bits().try_into().unwrap();
+        self.try_lptim_clk(lptim_clk)
+    }
+}
+
+impl Rcc {
+    /// Kernel clock of an LPTIM, `None` if the selected oscillator is off
+    fn try_lptim_clk(&self, lptim_clk: LptimSel) -> nb::Result<Option<Hertz>, Infallible> {
+        match lptim_clk {
+            LptimSel::Pclk => self.try_pclk1().map(Some),
+            LptimSel::Lsi => {
+                let csr = self.rcc.csr.read();
+
+                // LSI1 and LSI2 both run at 32 kHz
+                if csr.lsi1rdy().bit() || csr.lsi2rdy().bit() {
+                    Ok(Some(lsi1_hertz()))
+                } else {
+                    Ok(None)
+                }
+            }
+            LptimSel::Hsi16 => {
+                if self.rcc.cr.read().hsion().bit_is_clear() {
+                    Ok(None)
+                } else {
+                    Ok(Some(hsi16_hertz()))
+                }
+            }
+            LptimSel::Lse => {
+                if self.rcc.bdcr.read().lserdy().bit_is_clear() {
+                    Ok(None)
+                } else {
+                    Ok(Some(lse_hertz()))
+                }
+            }
+        }
+    }
 }
 
 impl TryClocks for &'_ Rcc {
@@ -1198,6 +2026,14 @@ impl TryClocks for &'_ Rcc {
     fn try_i2c3_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
         (*self).try_i2c3_clk()
     }
+
+    fn try_lptim1_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
+        (*self).try_lptim1_clk()
+    }
+
+    fn try_lptim2_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
+        (*self).try_lptim2_clk()
+    }
 }
 
 pub trait Clocks {
@@ -1209,6 +2045,8 @@ pub trait Clocks {
     fn pclk2(&self) -> Hertz;
     fn i2c1_clk(&self) -> Option<Hertz>;
     fn i2c3_clk(&self) -> Option<Hertz>;
+    fn lptim1_clk(&self) -> Option<Hertz>;
+    fn lptim2_clk(&self) -> Option<Hertz>;
 }
 
 impl<T> TryClocks for T
@@ -1246,9 +2084,53 @@ where
     fn try_i2c3_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
         Ok(self.i2c3_clk())
     }
+
+    fn try_lptim1_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
+        Ok(self.lptim1_clk())
+    }
+
+    fn try_lptim2_clk(&self) -> nb::Result<Option<Hertz>, Infallible> {
+        Ok(self.lptim2_clk())
+    }
 }
 
+/// Frozen clock configuration, see [`Rcc::freeze`]
+///
+/// The RCC is owned by this struct, so the clocks can't change anymore.
 pub struct Ccdr {
+    /// Clock frequencies
+    pub clocks: CoreClocks,
+    /// Peripheral clock enable / reset handles
+    pub peripheral: rec::Rec,
+    _rcc: RCC,
+}
+
+impl Ccdr {
+    /// Creates a frozen clock configuration from known frequencies, without reading them back
+    /// from the RCC
+    ///
+    /// For clocks configured outside of this HAL, e.g. by a bootloader or code generated by a
+    /// configuration tool.
+    ///
+    /// # Safety
+    ///
+    /// `clocks` must match the actual clock configuration, see
+    /// [`CoreClocks::new_unchecked`]. Drivers rely on [`TrustedClocks`] to compute their timings.
+    pub unsafe fn new_unchecked(rcc: RCC, clocks: CoreClocks) -> Self {
+        Self {
+            clocks,
+            peripheral: rec::Rec::new(),
+            _rcc: rcc,
+        }
+    }
+}
+
+/// Clock frequencies captured by [`Rcc::freeze`]
+///
+/// This is `Copy`, so it can be passed to a driver by value while the handle of the peripheral
+/// is borrowed from [`Ccdr::peripheral`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoreClocks {
     sysclk: Hertz,
     hclk1: Hertz,
     hclk2: Hertz,
@@ -1257,9 +2139,47 @@ pub struct Ccdr {
     pclk2: Hertz,
     i2c1_clk: Option<Hertz>,
     i2c3_clk: Option<Hertz>,
+    lptim1_clk: Option<Hertz>,
+    lptim2_clk: Option<Hertz>,
 }
 
-impl Clocks for Ccdr {
+impl CoreClocks {
+    /// Clock frequencies given by the caller, instead of captured by [`Rcc::freeze`]
+    ///
+    /// # Safety
+    ///
+    /// The frequencies must match the actual clock configuration, and the configuration must
+    /// not change as long as the value or one of its copies is in use. `None` means the kernel
+    /// clock of the peripheral is disabled.
+    #[allow(clippy::too_many_arguments)]
+    pub const unsafe fn new_unchecked(
+        sysclk: Hertz,
+        hclk1: Hertz,
+        hclk2: Hertz,
+        hclk4: Hertz,
+        pclk1: Hertz,
+        pclk2: Hertz,
+        i2c1_clk: Option<Hertz>,
+        i2c3_clk: Option<Hertz>,
+        lptim1_clk: Option<Hertz>,
+        lptim2_clk: Option<Hertz>,
+    ) -> Self {
+        Self {
+            sysclk,
+            hclk1,
+            hclk2,
+            hclk4,
+            pclk1,
+            pclk2,
+            i2c1_clk,
+            i2c3_clk,
+            lptim1_clk,
+            lptim2_clk,
+        }
+    }
+}
+
+impl Clocks for CoreClocks {
     fn sysclk(&self) -> Hertz {
         self.sysclk
     }
@@ -1291,6 +2211,56 @@ impl Clocks for Ccdr {
     fn i2c3_clk(&self) -> Option<Hertz> {
         self.i2c3_clk
     }
+
+    fn lptim1_clk(&self) -> Option<Hertz> {
+        self.lptim1_clk
+    }
+
+    fn lptim2_clk(&self) -> Option<Hertz> {
+        self.lptim2_clk
+    }
+}
+
+impl Clocks for Ccdr {
+    fn sysclk(&self) -> Hertz {
+        self.clocks.sysclk()
+    }
+
+    fn hclk1(&self) -> Hertz {
+        self.clocks.hclk1()
+    }
+
+    fn hclk2(&self) -> Hertz {
+        self.clocks.hclk2()
+    }
+
+    fn hclk4(&self) -> Hertz {
+        self.clocks.hclk4()
+    }
+
+    fn pclk1(&self) -> Hertz {
+        self.clocks.pclk1()
+    }
+
+    fn pclk2(&self) -> Hertz {
+        self.clocks.pclk2()
+    }
+
+    fn i2c1_clk(&self) -> Option<Hertz> {
+        self.clocks.i2c1_clk()
+    }
+
+    fn i2c3_clk(&self) -> Option<Hertz> {
+        self.clocks.i2c3_clk()
+    }
+
+    fn lptim1_clk(&self) -> Option<Hertz> {
+        self.clocks.lptim1_clk()
+    }
+
+    fn lptim2_clk(&self) -> Option<Hertz> {
+        self.clocks.lptim2_clk()
+    }
 }
 
 impl Clocks for &'_ Ccdr {
@@ -1325,6 +2295,14 @@ impl Clocks for &'_ Ccdr {
     fn i2c3_clk(&self) -> Option<Hertz> {
         (*self).i2c3_clk()
     }
+
+    fn lptim1_clk(&self) -> Option<Hertz> {
+        (*self).lptim1_clk()
+    }
+
+    fn lptim2_clk(&self) -> Option<Hertz> {
+        (*self).lptim2_clk()
+    }
 }
 
 pub struct Unwrap<T>(pub T);
@@ -1364,6 +2342,14 @@ where
     fn i2c3_clk(&self) -> Option<Hertz> {
         self.try_i2c3_clk().unwrap()
     }
+
+    fn lptim1_clk(&self) -> Option<Hertz> {
+        self.try_lptim1_clk().unwrap()
+    }
+
+    fn lptim2_clk(&self) -> Option<Hertz> {
+        self.try_lptim2_clk().unwrap()
+    }
 }
 
 pub struct Block<T>(pub T);
@@ -1402,6 +2388,14 @@ where
     fn i2c3_clk(&self) -> Option<Hertz> {
         nb::block!(self.try_i2c3_clk()).unwrap()
     }
+
+    fn lptim1_clk(&self) -> Option<Hertz> {
+        nb::block!(self.try_lptim1_clk()).unwrap()
+    }
+
+    fn lptim2_clk(&self) -> Option<Hertz> {
+        nb::block!(self.try_lptim2_clk()).unwrap()
+    }
 }
 
 /// Clocks are guaranteed to be valid for the provided lifetime
@@ -1411,6 +2405,7 @@ where
 /// The trait must only be implemented when the contract is upheld
 pub unsafe trait TrustedClocks<'a> {}
 
+unsafe impl TrustedClocks<'static> for CoreClocks {}
 unsafe impl TrustedClocks<'static> for Ccdr {}
 unsafe impl TrustedClocks<'static> for &'_ Ccdr {}
 unsafe impl TrustedClocks<'static> for Rcc {}
@@ -1422,6 +2417,33 @@ pub enum Stopwuck {
     Hsi16,
 }
 
+impl Stopwuck {
+    /// STOPWUCK bit value
+    fn bit(self) -> bool {
+        self == Self::Hsi16
+    }
+
+    fn from_bit(bit: bool) -> Self {
+        if bit {
+            Self::Hsi16
+        } else {
+            Self::Msi
+        }
+    }
+}
+
+/// Oscillators that can be enabled with [`Rcc::enable_oscillator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oscillator {
+    Msi,
+    Hsi16,
+    Hse,
+    Hsi48,
+    Lsi1,
+    Lsi2,
+    Lse,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RfClock {
     Hsi16,
@@ -1474,6 +2496,11 @@ impl MsiRange {
             Self::R48M => Hertz::Hz(48_000_000),
         }
     }
+
+    /// MSI in this range can clock the SMPS, see [`Smpssel::Msi`]
+    pub const fn smps_compatible(self) -> bool {
+        matches!(self, Self::R16M | Self::R24M | Self::R32M | Self::R48M)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
@@ -1557,6 +2584,24 @@ impl PpreScaler {
             Self::D16 => 16,
         }
     }
+
+    /// Smallest divider bringing `input` down to at most `target`
+    ///
+    /// Saturates at [`D16`](Self::D16) if `target` can't be reached.
+    pub fn for_target(input: Hertz, target: Hertz) -> Self {
+        const ASCENDING: [PpreScaler; 5] = [
+            PpreScaler::D1,
+            PpreScaler::D2,
+            PpreScaler::D4,
+            PpreScaler::D8,
+            PpreScaler::D16,
+        ];
+
+        ASCENDING
+            .into_iter()
+            .find(|div| divides_to(input, target, div.div_scale().into()))
+            .unwrap_or(Self::D16)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, FromPrimitive, IntoPrimitive)]
@@ -1613,6 +2658,38 @@ impl PreScaler {
             Self::D512 => 512,
         }
     }
+
+    /// Smallest divider bringing `input` down to at most `target`
+    ///
+    /// Saturates at [`D512`](Self::D512) if `target` can't be reached.
+    pub fn for_target(input: Hertz, target: Hertz) -> Self {
+        const ASCENDING: [PreScaler; 14] = [
+            PreScaler::D1,
+            PreScaler::D2,
+            PreScaler::D3,
+            PreScaler::D4,
+            PreScaler::D5,
+            PreScaler::D6,
+            PreScaler::D8,
+            PreScaler::D10,
+            PreScaler::D16,
+            PreScaler::D32,
+            PreScaler::D64,
+            PreScaler::D128,
+            PreScaler::D256,
+            PreScaler::D512,
+        ];
+
+        ASCENDING
+            .into_iter()
+            .find(|div| divides_to(input, target, div.div_scale().into()))
+            .unwrap_or(Self::D512)
+    }
+}
+
+/// `input / div <= target`
+fn divides_to(input: Hertz, target: Hertz, div: u64) -> bool {
+    u64::from(input.raw()) <= u64::from(target.raw()) * div
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
@@ -1633,6 +2710,17 @@ pub enum PllSrc {
     Hse = 0b11,
 }
 
+/// Complete main PLL configuration, see [`Rcc::configure_pll`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PllConfig {
+    pub src: PllSrc,
+    pub m: Pllm,
+    pub n: Plln,
+    pub p: Pllp,
+    pub q: PllQR,
+    pub r: PllQR,
+}
+
 /// Division factor for the main PLL and audio PLLSAI1 input clock
 ///
 /// The software has to set these bits to ensure that the VCO input frequency
@@ -1690,11 +2778,39 @@ impl Plln {
         Ok(Self(x))
     }
 
+    /// Multiplication factor bringing `vco_in` closest to `target`
+    ///
+    /// Fails if the factor is out of range or the resulting VCO frequency is outside of
+    /// [[`PLL_VCO_MIN`], [`PLL_VCO_MAX`]].
+    pub fn for_vco(vco_in: VcoHertz, target: VcoHertz) -> Result<Self, ValueError> {
+        let plln = Self::new(nearest_factor(vco_in, target)?)?;
+
+        let vco_out = vco_in.raw().checked_mul(u32::from(plln.0)).map(VcoHertz::from_raw);
+
+        if !vco_out.is_some_and(|v| (PLL_VCO_MIN..=PLL_VCO_MAX).contains(&v)) {
+            return value_error!("Main PLL VCO frequency must be in range of [96, 344] MHz");
+        }
+
+        Ok(plln)
+    }
+
     pub fn get(self) -> u8 {
         self.0
     }
 }
 
+/// Factor `n` minimizing the distance between `vco_in * n` and `target`
+fn nearest_factor(vco_in: VcoHertz, target: VcoHertz) -> Result<u8, ValueError> {
+    if vco_in.raw() == 0 {
+        return value_error!("VCO input frequency must not be 0");
+    }
+
+    let vco_in = u64::from(vco_in.raw());
+    let n = (u64::from(target.raw()) + vco_in / 2) / vco_in;
+
+    u8::try_from(n).or(value_error!("Multiplication factor out of range"))
+}
+
 impl TryFrom<u8> for Plln {
     type Error = ValueError;
 
@@ -1709,6 +2825,7 @@ impl From<Plln> for u8 {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Pllp(u8);
 
 impl Pllp {
@@ -1797,6 +2914,22 @@ impl Pllsai1N {
         Ok(Self(x))
     }
 
+    /// Multiplication factor bringing `vco_in` closest to `target`
+    ///
+    /// Fails if the factor is out of range or the resulting VCO frequency is outside of
+    /// [[`PLLSAI1_VCO_MIN`], [`PLLSAI1_VCO_MAX`]].
+    pub fn for_vco(vco_in: VcoHertz, target: VcoHertz) -> Result<Self, ValueError> {
+        let plln = Self::new(nearest_factor(vco_in, target)?)?;
+
+        let vco_out = vco_in.raw().checked_mul(u32::from(plln.0)).map(VcoHertz::from_raw);
+
+        if !vco_out.is_some_and(|v| (PLLSAI1_VCO_MIN..=PLLSAI1_VCO_MAX).contains(&v)) {
+            return value_error!("PLLSAI1 VCO frequency must be in range of [64, 344] MHz");
+        }
+
+        Ok(plln)
+    }
+
     pub fn get(self) -> u8 {
         self.0
     }
@@ -1924,6 +3057,161 @@ pub enum RngSel {
     Lse = 0b10,
 }
 
+/// CRS synchronization source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrsSyncSource {
+    /// CRS_SYNC pin with the given frequency
+    Gpio(Hertz),
+    /// LSE (32.768 kHz)
+    Lse,
+    /// USB start of frame (1 kHz)
+    UsbSof,
+}
+
+impl CrsSyncSource {
+    pub fn hertz(self) -> Hertz {
+        match self {
+            Self::Gpio(f) => f,
+            Self::Lse => lse_hertz(),
+            Self::UsbSof => Hertz::kHz(1),
+        }
+    }
+
+    fn syncsrc(self) -> u8 {
+        match self {
+            Self::Gpio(_) => 0b00,
+            Self::Lse => 0b01,
+            Self::UsbSof => 0b10,
+        }
+    }
+}
+
+/// CRS RELOAD and FELIM values for trimming `target` against `sync`, see RM0434 Rev 9 p. 267
+fn crs_reload_felim(target: Hertz, sync: Hertz) -> Result<(u16, u8), Error> {
+    if sync.raw() == 0 || sync > target {
+        return Err(Error::CrsSyncIllegalFrequency);
+    }
+
+    let ratio = target.raw() / sync.raw();
+    let reload = u16::try_from(ratio - 1).map_err(|_| Error::CrsSyncIllegalFrequency)?;
+    // FELIM = (fTARGET / fSYNC) * STEP / 2, with a trimming step of 0.14 %. At most 46 with
+    // RELOAD in range.
+    let felim = ((ratio * 14 + 19_999) / 20_000) as u8;
+
+    Ok((reload, felim))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum RtcSel {
+    NoClock = 0b00,
+    Lse = 0b01,
+    Lsi = 0b10,
+    HseDiv32 = 0b11,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LscoSel {
+    Lsi,
+    Lse,
+}
+
+/// Clock tree snapshot, see [`Rcc::dump_clock_tree`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockTree {
+    pub clocks: CoreClocks,
+    /// Frequency output on MCO, `None` if disabled
+    pub mco: Option<Hertz>,
+    pub low_speed: LowSpeedStatus,
+}
+
+impl fmt::Display for ClockTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = &self.clocks;
+
+        write!(
+            f,
+            "sysclk: {} Hz, hclk1: {} Hz, hclk2: {} Hz, hclk4: {} Hz, pclk1: {} Hz, pclk2: {} Hz",
+            c.sysclk.raw(),
+            c.hclk1.raw(),
+            c.hclk2.raw(),
+            c.hclk4.raw(),
+            c.pclk1.raw(),
+            c.pclk2.raw(),
+        )?;
+
+        for (name, clk) in [
+            ("i2c1", c.i2c1_clk),
+            ("i2c3", c.i2c3_clk),
+            ("lptim1", c.lptim1_clk),
+            ("lptim2", c.lptim2_clk),
+            ("mco", self.mco),
+        ] {
+            match clk {
+                Some(clk) => write!(f, ", {}: {} Hz", name, clk.raw())?,
+                None => write!(f, ", {}: off", name)?,
+            }
+        }
+
+        write!(f, ", rtc: {:?}", self.low_speed.rtc_clock)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ClockTree {
+    fn format(&self, f: defmt::Formatter) {
+        let c = &self.clocks;
+
+        defmt::write!(
+            f,
+            "ClockTree {{ sysclk: {=u32} Hz, hclk1: {=u32} Hz, hclk2: {=u32} Hz, hclk4: {=u32} Hz, \
+            pclk1: {=u32} Hz, pclk2: {=u32} Hz, i2c1: {}, i2c3: {}, lptim1: {}, lptim2: {}, \
+            mco: {}, low_speed: {} }}",
+            c.sysclk.raw(),
+            c.hclk1.raw(),
+            c.hclk2.raw(),
+            c.hclk4.raw(),
+            c.pclk1.raw(),
+            c.pclk2.raw(),
+            c.i2c1_clk.map(Hertz::raw),
+            c.i2c3_clk.map(Hertz::raw),
+            c.lptim1_clk.map(Hertz::raw),
+            c.lptim2_clk.map(Hertz::raw),
+            self.mco.map(Hertz::raw),
+            self.low_speed,
+        )
+    }
+}
+
+/// Low speed oscillator status, see [`Rcc::low_speed_status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LowSpeedStatus {
+    pub lse_on: bool,
+    pub lse_ready: bool,
+    pub lse_bypass: bool,
+    /// LSE failure detected by LSECSS (LSECSSD)
+    pub lse_css_failed: bool,
+    pub lsi1_on: bool,
+    pub lsi1_ready: bool,
+    pub lsi2_on: bool,
+    pub lsi2_ready: bool,
+    pub rtc_clock: RtcSel,
+    /// Clock on the LSCO pin, `None` if LSCO is disabled
+    pub lsco: Option<LscoSel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum LseDrive {
+    Low = 0b00,
+    MediumLow = 0b01,
+    MediumHigh = 0b10,
+    High = 0b11,
+}
+
 /// MSI Maximum frequency
 pub const fn msi_max_hertz(vos: Vos) -> Hertz {
     match vos {
@@ -1970,18 +3258,150 @@ pub const fn pll_max_hertz(vos: Vos) -> Hertz {
 /// 32 kHz low speed internal RC which may drive the independent watchdog
 /// and optionally the RTC used for Auto-wakeup from Stop and Standby modes
 pub const fn lsi1_hertz() -> Hertz {
-    Hertz::MHz(32)
+    Hertz::kHz(32)
 }
 
 /// 32 kHz low speed low drift internal RC which may drive the independent watchdog
 /// and optionally the RTC used for Auto-wakeup from Stop and Standby modes
 pub const fn lsi2_hertz() -> Hertz {
-    Hertz::MHz(32)
+    Hertz::kHz(32)
 }
 
 /// Low speed external crystal which optionally drives the RTC used for
 /// Auto-wakeup or the RF system Auto-wakeup from Stop and Standby modes, or the
 /// real-time clock (RTCCLK)
 pub const fn lse_hertz() -> Hertz {
-    Hertz::kHz(32_768)
+    Hertz::Hz(32_768)
+}
+
+#[cfg(all(test, not(test)))]
+mod test {
+    use super::{
+        crs_reload_felim, Error, Hertz, MsiRange, Plln, Pllsai1N, PpreScaler, PreScaler, Stopwuck,
+        VcoHertz,
+    };
+    use fugit::RateExtU32;
+
+    #[test]
+    /// Test that the STOPWUCK encoding round-trips and matches RM0434 (0: MSI, 1: HSI16)
+    fn stopwuck_round_trip() {
+        for clk in [Stopwuck::Msi, Stopwuck::Hsi16] {
+            assert_eq!(Stopwuck::from_bit(clk.bit()), clk);
+        }
+
+        assert!(!Stopwuck::Msi.bit());
+        assert!(Stopwuck::Hsi16.bit());
+    }
+
+    #[test]
+    /// Test that `for_target` picks the smallest divider reaching the target, for each divider
+    fn prescaler_for_target() {
+        let input = 64.MHz::<1, 1>();
+
+        for div in [
+            PreScaler::D1,
+            PreScaler::D2,
+            PreScaler::D3,
+            PreScaler::D4,
+            PreScaler::D5,
+            PreScaler::D6,
+            PreScaler::D8,
+            PreScaler::D10,
+            PreScaler::D16,
+            PreScaler::D32,
+            PreScaler::D64,
+            PreScaler::D128,
+            PreScaler::D256,
+            PreScaler::D512,
+        ] {
+            let exact = input / u32::from(div.div_scale());
+            assert_eq!(PreScaler::for_target(input, exact), div);
+        }
+
+        // 64 MHz / 5 = 12.8 MHz is just above, 64 MHz / 6 just below 11 MHz
+        assert_eq!(PreScaler::for_target(input, 11.MHz()), PreScaler::D6);
+        // 64 MHz / 10 = 6.4 MHz
+        assert_eq!(PreScaler::for_target(input, 7.MHz()), PreScaler::D10);
+        assert_eq!(PreScaler::for_target(input, 22.MHz()), PreScaler::D3);
+        assert_eq!(PreScaler::for_target(input, 100.MHz()), PreScaler::D1);
+        assert_eq!(PreScaler::for_target(input, 1.kHz()), PreScaler::D512);
+    }
+
+    #[test]
+    fn ppre_scaler_for_target() {
+        let input = 64.MHz::<1, 1>();
+
+        for div in [
+            PpreScaler::D1,
+            PpreScaler::D2,
+            PpreScaler::D4,
+            PpreScaler::D8,
+            PpreScaler::D16,
+        ] {
+            let exact = input / u32::from(div.div_scale());
+            assert_eq!(PpreScaler::for_target(input, exact), div);
+        }
+
+        assert_eq!(PpreScaler::for_target(input, 20.MHz()), PpreScaler::D4);
+        assert_eq!(PpreScaler::for_target(input, 1.MHz()), PpreScaler::D16);
+    }
+
+    #[test]
+    fn pll_n_for_vco() {
+        // HSE 32 MHz / PLLM 8
+        let vco_in = VcoHertz::MHz(4);
+
+        assert_eq!(Plln::for_vco(vco_in, VcoHertz::MHz(256)).unwrap().get(), 64);
+        // Rounds to the nearest factor
+        assert_eq!(Plln::for_vco(vco_in, VcoHertz::MHz(257)).unwrap().get(), 64);
+        assert_eq!(Plln::for_vco(vco_in, VcoHertz::MHz(259)).unwrap().get(), 65);
+
+        // VCO window
+        assert!(Plln::for_vco(vco_in, VcoHertz::MHz(92)).is_err());
+        assert!(Plln::for_vco(vco_in, VcoHertz::MHz(348)).is_err());
+        assert!(Pllsai1N::for_vco(vco_in, VcoHertz::MHz(64)).is_ok());
+        assert!(Pllsai1N::for_vco(vco_in, VcoHertz::MHz(60)).is_err());
+
+        // Factor out of range
+        assert!(Plln::for_vco(VcoHertz::MHz(16), VcoHertz::MHz(80)).is_err());
+        assert!(Plln::for_vco(VcoHertz::from_raw(0), VcoHertz::MHz(128)).is_err());
+
+        // The VCO frequency overflows u32, n = 7
+        let (vco_in, target) = (VcoHertz::from_raw(650_000_000), VcoHertz::from_raw(u32::MAX));
+        assert!(Plln::for_vco(vco_in, target).is_err());
+        assert!(Pllsai1N::for_vco(vco_in, target).is_err());
+    }
+
+    #[test]
+    /// Test that exactly the 16 - 48 MHz MSI ranges can clock the SMPS
+    fn msi_range_smps_compatible() {
+        for bits in 0..=0b1011 {
+            let range = MsiRange::try_from(bits).unwrap();
+
+            assert_eq!(
+                range.smps_compatible(),
+                range.hertz().raw() >= 16_000_000,
+                "{:?}",
+                range
+            );
+        }
+    }
+
+    #[test]
+    /// Test the CRS RELOAD / FELIM computation and its frequency range
+    fn crs_sync_frequency() {
+        let target = Hertz::MHz(48);
+
+        // USB SOF, the reset values of RM0434
+        assert_eq!(crs_reload_felim(target, Hertz::kHz(1)).unwrap(), (47_999, 34));
+        assert_eq!(crs_reload_felim(target, Hertz::Hz(733)).unwrap(), (65_483, 46));
+        assert_eq!(crs_reload_felim(target, target).unwrap(), (0, 1));
+
+        for sync in [Hertz::Hz(0), Hertz::Hz(732), Hertz::MHz(49)] {
+            assert!(matches!(
+                crs_reload_felim(target, sync),
+                Err(Error::CrsSyncIllegalFrequency)
+            ));
+        }
+    }
 }
@@ -353,3 +353,25 @@ rec_struct! {
     #[cfg(feature = "cm0p")]
     BLE,
 }
+
+/// Enable bits left set by [`Rec::disable_all`]: SRAM1 (AHB1, CPU2 only)
+const AHB1_SAFELIST: u32 = 1 << 9;
+/// Enable bits left set by [`Rec::disable_all`]: HSEM, IPCC and FLASH (AHB3)
+const AHB3_SAFELIST: u32 = (1 << 19) | (1 << 20) | (1 << 25);
+/// Enable bits left set by [`Rec::disable_all`]: RTCAPB (APB1)
+const APB1_1_SAFELIST: u32 = 1 << 10;
+
+impl Rec {
+    /// Clears the AHB / APB clock enable bits of the current core, except the safelists
+    pub(super) fn disable_all(&mut self) {
+        AHB1::enr().modify(|r, w| unsafe { w.bits(r.bits() & AHB1_SAFELIST) });
+        AHB2::enr().modify(|_, w| unsafe { w.bits(0) });
+        AHB3::enr().modify(|r, w| unsafe { w.bits(r.bits() & AHB3_SAFELIST) });
+        APB1_1::enr().modify(|r, w| unsafe { w.bits(r.bits() & APB1_1_SAFELIST) });
+        APB1_2::enr().modify(|_, w| unsafe { w.bits(0) });
+        APB2::enr().modify(|_, w| unsafe { w.bits(0) });
+
+        #[cfg(feature = "cm0p")]
+        APB3::enr().modify(|_, w| unsafe { w.bits(0) });
+    }
+}
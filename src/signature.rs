@@ -1,6 +1,9 @@
+use crate::flash::FlashUid;
+use core::fmt;
 use core::str::from_utf8_unchecked;
+use num_enum::FromPrimitive;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct Uid {
     x: u16,
@@ -27,10 +30,24 @@ impl Uid {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Uid {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Uid {{ x: {=u16}, y: {=u16}, waf_num: {=u8}, lot_num: {=str} }}",
+            self.x(),
+            self.y(),
+            self.waf_num(),
+            self.lot_num(),
+        )
+    }
+}
+
 // See RM0434 Rev 9 p. 1511
 define_ptr_type!(Uid, 0x1FFF_7590);
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct FlashSize(u16);
 
@@ -44,5 +61,64 @@ impl FlashSize {
     }
 }
 
+impl fmt::Display for FlashSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} KiB", self.kilo_bytes())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for FlashSize {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u16} KiB", self.kilo_bytes())
+    }
+}
+
 // See RM0434 Rev 9 p. 1512
 define_ptr_type!(FlashSize, 0x1FFF_75E0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Package(u16);
+
+impl Package {
+    pub fn package_type(&self) -> PackageType {
+        ((self.0 & 0x1F) as u8).into()
+    }
+}
+
+// See RM0434 Rev 9 p. 1513
+define_ptr_type!(Package, 0x1FFF_7500);
+
+/// Package type (PKG)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum PackageType {
+    Wlcsp100Ufbga129 = 0b10001,
+    Vfqfpn68 = 0b10011,
+    Ufqfpn48 = 0b11010,
+    #[num_enum(catch_all)]
+    Other(u8),
+}
+
+/// Device fingerprint, gathering the identification data of the device
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceId {
+    pub uid: Uid,
+    pub flash_uid: u64,
+    pub flash_kib: u16,
+    pub package: PackageType,
+}
+
+impl DeviceId {
+    pub fn read() -> Self {
+        Self {
+            uid: *Uid::get(),
+            flash_uid: FlashUid::get().uid64(),
+            flash_kib: FlashSize::get().kilo_bytes(),
+            package: Package::get().package_type(),
+        }
+    }
+}
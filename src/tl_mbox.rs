@@ -1,19 +1,60 @@
+//! Transport layer mailbox to the wireless stack on CPU2
+//!
+//! The buffers shared with CPU2 are placed in three link sections, which the linker script must
+//! define:
+//!
+//! - `TL_REF_TABLE`: the reference table, CPU2 expects it at the start of SRAM2a (`0x2003_0000`)
+//! - `MB_MEM1`: the tables and queues
+//! - `MB_MEM2`: the command and event buffers
+//!
+//! All three must be `NOLOAD` and located in the SRAM2a / SRAM2b range not secured for CPU2,
+//! `MB_MEM1` and `MB_MEM2` must not overlap the reference table. For example:
+//!
+//! ```text
+//! MEMORY
+//! {
+//!   RAM_SHARED1 (xrw) : ORIGIN = 0x20030000, LENGTH = 0x28
+//!   RAM_SHARED2 (xrw) : ORIGIN = 0x20030028, LENGTH = 0x27D8
+//! }
+//!
+//! SECTIONS {
+//!     TL_REF_TABLE (NOLOAD) : { *(TL_REF_TABLE) } >RAM_SHARED1
+//!     MB_MEM1 (NOLOAD) : { *(MB_MEM1) } >RAM_SHARED2
+//!     MB_MEM2 (NOLOAD) : { _sMB_MEM2 = . ; *(MB_MEM2) ; _eMB_MEM2 = . ; } >RAM_SHARED2
+//! }
+//! ```
+//!
+//! The sections can be moved within SRAM2 to fit a custom memory map. [`TlMbox::tl_init`] checks
+//! the placement and panics on a mismatch, instead of letting CPU2 corrupt the application's
+//! memory.
+
 pub mod acl;
 pub mod ble;
 pub mod channel;
 pub mod cmd;
 pub mod consts;
 pub mod evt;
+pub mod hci;
 pub mod mm;
+pub mod sys;
+pub mod thread;
+pub mod traces;
 pub mod unsafe_linked_list;
 
 use self::acl::AclDataPacket;
 use self::ble::Ble;
 use self::evt::EvtBox;
+use self::hci::BleHci;
 use self::mm::MemoryManager;
+use self::sys::Sys;
+use self::thread::{Thread, ThreadNotifQueue};
+use self::traces::Traces;
 use self::{cmd::CmdPacket, unsafe_linked_list::ListNode};
 use crate::{ipcc::Ipcc, rcc::rec};
 use aligned::{Aligned, A4};
+use channel::c2::{
+    IPCC_BLE_EVENT_CHANNEL, IPCC_THREAD_NOTIFICATION_ACK_CHANNEL, IPCC_TRACES_CHANNEL,
+};
 use consts::{TL_CS_EVT_SIZE, TL_EVT_HDR_SIZE, TL_PACKET_HEADER_SIZE};
 use core::mem::MaybeUninit;
 
@@ -126,6 +167,11 @@ pub struct RefTable {
     p_ble_lld_table: *const BleLldTable,
 }
 
+/// Start of SRAM2a, where CPU2 looks for the reference table
+const SRAM2A_BASE: usize = 0x2003_0000;
+/// End of SRAM2b
+const SRAM2_END: usize = 0x2004_0000;
+
 #[link_section = "TL_REF_TABLE"]
 static mut TL_REF_TABLE: MaybeUninit<RefTable> = MaybeUninit::uninit();
 
@@ -194,12 +240,22 @@ const fn divc(x: usize, y: usize) -> usize {
     (x + y - 1) / y
 }
 
-const POOL_SIZE: usize =
-    CFG_TLBLE_EVT_QUEUE_LENGTH * 4 * divc(TL_PACKET_HEADER_SIZE + TL_BLE_EVENT_FRAME_SIZE, 4);
+/// Size of a single event buffer in the pools, rounded up to a multiple of 4
+const EVT_BUF_SIZE: usize = 4 * divc(TL_PACKET_HEADER_SIZE + TL_BLE_EVENT_FRAME_SIZE, 4);
+
+const POOL_SIZE: usize = CFG_TLBLE_EVT_QUEUE_LENGTH * EVT_BUF_SIZE;
 
 #[link_section = "MB_MEM2"]
 static mut EVT_POOL: Aligned<A4, MaybeUninit<[u8; POOL_SIZE]>> = Aligned(MaybeUninit::uninit());
 
+const CFG_TL_TRACES_EVT_QUEUE_LENGTH: usize = 2;
+
+const TRACES_POOL_SIZE: usize = CFG_TL_TRACES_EVT_QUEUE_LENGTH * EVT_BUF_SIZE;
+
+#[link_section = "MB_MEM2"]
+static mut TRACES_EVT_POOL: Aligned<A4, MaybeUninit<[u8; TRACES_POOL_SIZE]>> =
+    Aligned(MaybeUninit::uninit());
+
 #[link_section = "MB_MEM2"]
 static mut SYS_CMD_BUFFER: Aligned<A4, MaybeUninit<CmdPacket>> = Aligned(MaybeUninit::uninit());
 
@@ -223,15 +279,97 @@ static mut BLE_CMD_BUFFER: Aligned<A4, MaybeUninit<CmdPacket>> = Aligned(MaybeUn
 static mut HCI_ACL_DATA_BUFFER: Aligned<A4, MaybeUninit<[u8; TL_PACKET_HEADER_SIZE + 5 + 251]>> =
     Aligned(MaybeUninit::uninit());
 
+#[link_section = "MB_MEM2"]
+static mut THREAD_OT_CMD_RSP_BUFFER: Aligned<A4, MaybeUninit<CmdPacket>> =
+    Aligned(MaybeUninit::uninit());
+
+#[link_section = "MB_MEM2"]
+static mut THREAD_NOTIF_ACK_BUFFER: Aligned<A4, MaybeUninit<CmdPacket>> =
+    Aligned(MaybeUninit::uninit());
+
+#[link_section = "MB_MEM2"]
+static mut THREAD_CLI_CMD_BUFFER: Aligned<A4, MaybeUninit<CmdPacket>> =
+    Aligned(MaybeUninit::uninit());
+
+#[link_section = "MB_MEM2"]
+static mut THREAD_CLI_NOT_BUFFER: Aligned<A4, MaybeUninit<CmdPacket>> =
+    Aligned(MaybeUninit::uninit());
+
 pub type HeaplessEvtQueue = heapless::spsc::Queue<EvtBox, 32>;
 
 pub struct TlMbox {
     ble: Ble,
     mm: MemoryManager,
+    sys: Sys,
+    thread: Thread,
+    traces: Traces,
+}
+
+/// Asserts that the statics are placed in shared RAM after the reference table
+macro_rules! assert_in_shared_ram {
+    ($($STATIC:ident),* $(,)?) => {
+        $(
+            let start = core::ptr::addr_of!($STATIC) as usize;
+            let end = start + core::mem::size_of_val(&*core::ptr::addr_of!($STATIC));
+
+            assert!(
+                start >= SRAM2A_BASE + core::mem::size_of::<RefTable>() && end <= SRAM2_END,
+                concat!(stringify!($STATIC), " is not placed in SRAM2, check the MB_MEM1 / MB_MEM2 sections"),
+            );
+        )*
+    };
+}
+
+/// Checks the placement of the link sections, see the [module documentation](self)
+fn check_placement() {
+    // SAFETY: Only the addresses of the statics are taken
+    unsafe {
+        assert!(
+            core::ptr::addr_of!(TL_REF_TABLE) as usize == SRAM2A_BASE,
+            "TL_REF_TABLE is not placed at the start of SRAM2a",
+        );
+
+        assert_in_shared_ram!(
+            TL_DEVICE_INTO_TABLE,
+            TL_BLE_TABLE,
+            TL_THREAD_TABLE,
+            TL_LLD_TESTS_TABLE,
+            TL_BLE_LLD_TABLE,
+            TL_SYS_TABLE,
+            TL_MEM_MANAGER_TABLE,
+            TL_TRACES_TABLE,
+            TL_MAC_802_15_4_TABLE,
+            TL_ZIGBEE_TABLE,
+            FREE_BUF_QUEUE,
+            TRACES_EVT_QUEUE,
+            CS_BUFFER,
+            EVT_QUEUE,
+            SYSTEM_EVT_QUEUE,
+            EVT_POOL,
+            TRACES_EVT_POOL,
+            SYS_CMD_BUFFER,
+            SYS_SPARE_EVT_BUF,
+            BLE_SPARE_EVT_BUF,
+            BLE_CMD_BUFFER,
+            HCI_ACL_DATA_BUFFER,
+            THREAD_OT_CMD_RSP_BUFFER,
+            THREAD_NOTIF_ACK_BUFFER,
+            THREAD_CLI_CMD_BUFFER,
+            THREAD_CLI_NOT_BUFFER,
+        );
+    }
 }
 
 impl TlMbox {
+    /// Initializes the shared tables and buffers and the IPCC
+    ///
+    /// # Panics
+    ///
+    /// Panics if the link sections aren't placed in SRAM2, see the
+    /// [module documentation](self)
     pub fn tl_init(ipcc: crate::pac::IPCC, rec: &mut rec::IPCC) -> (Self, Ipcc) {
+        check_placement();
+
         unsafe {
             TL_REF_TABLE = MaybeUninit::new(RefTable {
                 p_device_info_table: TL_DEVICE_INTO_TABLE.as_ptr(),
@@ -260,20 +398,128 @@ impl TlMbox {
             LOCAL_FREE_BUF_QUEUE = MaybeUninit::zeroed();
 
             EVT_POOL = Aligned(MaybeUninit::zeroed());
+            TRACES_EVT_POOL = Aligned(MaybeUninit::zeroed());
             SYS_CMD_BUFFER = Aligned(MaybeUninit::zeroed());
             SYS_SPARE_EVT_BUF = Aligned(MaybeUninit::zeroed());
             BLE_SPARE_EVT_BUF = Aligned(MaybeUninit::zeroed());
             CS_BUFFER = Aligned(MaybeUninit::zeroed());
             BLE_CMD_BUFFER = Aligned(MaybeUninit::zeroed());
+            THREAD_OT_CMD_RSP_BUFFER = Aligned(MaybeUninit::zeroed());
+            THREAD_NOTIF_ACK_BUFFER = Aligned(MaybeUninit::zeroed());
+            THREAD_CLI_CMD_BUFFER = Aligned(MaybeUninit::zeroed());
+            THREAD_CLI_NOT_BUFFER = Aligned(MaybeUninit::zeroed());
         }
 
         let mut ipcc = Ipcc::new(ipcc, rec);
 
         let ble = Ble::new(&mut ipcc);
         let mm = MemoryManager::new();
-
-        let s = Self { ble, mm };
+        let sys = Sys::new(&mut ipcc);
+        let thread = Thread::new(&mut ipcc);
+        let traces = Traces::new(&mut ipcc);
+
+        let s = Self {
+            ble,
+            mm,
+            sys,
+            thread,
+            traces,
+        };
 
         (s, ipcc)
     }
+
+    pub fn mm(&self) -> &MemoryManager {
+        &self.mm
+    }
+
+    pub fn sys(&mut self) -> &mut Sys {
+        &mut self.sys
+    }
+
+    pub fn thread(&mut self) -> &mut Thread {
+        &mut self.thread
+    }
+
+    pub fn ble(&mut self) -> &mut Ble {
+        &mut self.ble
+    }
+
+    /// BLE channel as an HCI transport, see [`hci`]
+    pub fn ble_hci(&mut self) -> BleHci<'_> {
+        BleHci::new(&mut self.ble)
+    }
+
+    /// CPU2 has populated the device info table, i.e. a firmware is running on it
+    ///
+    /// The table is cleared by [`tl_init`](Self::tl_init) and filled in by CPU2 before it sends
+    /// the system ready event. Call this once CPU2 had time to boot, e.g. after
+    /// [`Sys::wait_ready_timeout`] failed, to tell a missing firmware from a slow start.
+    pub fn coprocessor_present(&self) -> bool {
+        // See `safe_boot_version` for the alignment
+        unsafe {
+            let table = TL_DEVICE_INTO_TABLE.as_ptr();
+
+            let safe_boot = core::ptr::addr_of!((*table).safe_boot_info_table.version);
+            let fus = core::ptr::addr_of!((*table).fus_info_table.version);
+            let wireless_fw = core::ptr::addr_of!((*table).wireless_fw_info_table.version);
+
+            [safe_boot, fus, wireless_fw]
+                .into_iter()
+                .any(|version| version.read_volatile() != 0)
+        }
+    }
+
+    /// Version of the safe boot (FUS bootloader), as reported by CPU2
+    ///
+    /// The device info table is filled in by CPU2 on startup, this is 0 until the system ready
+    /// event has been received.
+    pub fn safe_boot_version(&self) -> u32 {
+        // The table is 4-byte aligned and only holds u32 fields, so despite `packed` the
+        // fields are aligned
+        unsafe {
+            let table = TL_DEVICE_INTO_TABLE.as_ptr();
+            core::ptr::addr_of!((*table).safe_boot_info_table.version).read_volatile()
+        }
+    }
+
+    /// Memory size used by the FUS, as reported by CPU2
+    ///
+    /// Like [`safe_boot_version`](Self::safe_boot_version), only valid once CPU2 is ready.
+    pub fn fus_memory_size(&self) -> u32 {
+        unsafe {
+            let table = TL_DEVICE_INTO_TABLE.as_ptr();
+            core::ptr::addr_of!((*table).fus_info_table.memory_size).read_volatile()
+        }
+    }
+
+    /// Drains the CPU2 trace queue
+    ///
+    /// Call this from the `IPCC_C1_RX_IT` handler. Received trace packets are pushed into
+    /// `queue`, their buffers are released when the [`EvtBox`]es are dropped.
+    pub fn traces_evt_handler(&mut self, ipcc: &mut Ipcc, queue: &mut HeaplessEvtQueue) {
+        if ipcc.is_rx_pending(IPCC_TRACES_CHANNEL) {
+            self.traces.evt_handler(ipcc, queue);
+        }
+    }
+
+    /// Drains the BLE event queue
+    ///
+    /// Call this from the `IPCC_C1_RX_IT` handler. Received events are pushed into `queue`, their
+    /// buffers are released when the [`EvtBox`]es are dropped.
+    pub fn ble_evt_handler(&mut self, ipcc: &mut Ipcc, queue: &mut HeaplessEvtQueue) {
+        if ipcc.is_rx_pending(IPCC_BLE_EVENT_CHANNEL) {
+            self.ble.evt_handler(ipcc, queue);
+        }
+    }
+
+    /// Handles a pending Thread notification
+    ///
+    /// Call this from the `IPCC_C1_RX_IT` handler. The notification is copied into `queue` and
+    /// acknowledged to CPU2.
+    pub fn thread_notif_handler(&mut self, ipcc: &mut Ipcc, queue: &mut ThreadNotifQueue) {
+        if ipcc.is_rx_pending(IPCC_THREAD_NOTIFICATION_ACK_CHANNEL) {
+            self.thread.notif_handler(ipcc, queue);
+        }
+    }
 }
@@ -15,6 +15,8 @@ use core::marker::PhantomData;
 use core::mem::MaybeUninit;
 
 pub struct Ble {
+    /// Commands the controller can accept, see [`can_send_cmd`](Self::can_send_cmd)
+    numcmd: u8,
     _marker: PhantomData<*const ()>,
 }
 
@@ -34,11 +36,25 @@ impl Ble {
         ipcc.c1_set_rx_channel(channel::c2::IPCC_BLE_EVENT_CHANNEL, true);
 
         Self {
+            // The controller accepts one command after reset
+            numcmd: 1,
             _marker: PhantomData,
         }
     }
 
+    /// The controller can accept another command
+    ///
+    /// Each [`send_cmd`](Self::send_cmd) uses up a command slot, the controller grants new ones
+    /// with the command status and command complete events, which are tracked by
+    /// [`TlMbox::ble_evt_handler`](super::TlMbox::ble_evt_handler). Sending a command otherwise
+    /// overruns the command buffer shared with CPU2.
+    pub fn can_send_cmd(&self) -> bool {
+        self.numcmd > 0
+    }
+
     pub fn send_cmd(&mut self, ipcc: &mut Ipcc, buf: &[u8]) {
+        self.numcmd = self.numcmd.saturating_sub(1);
+
         unsafe {
             let p_cmd_buffer = (*TL_BLE_TABLE.as_mut_ptr()).pcmd_buffer;
             let p_cmd_serial: *mut _ = &mut (*p_cmd_buffer).cmdserial;
@@ -84,6 +100,10 @@ impl Ble {
                 let event: *mut EvtPacket = node_ptr.cast();
                 let event = EvtBox::new(event);
 
+                if let Some(numcmd) = event.as_packet().numcmd() {
+                    self.numcmd = numcmd;
+                }
+
                 queue
                     .enqueue(event)
                     .unwrap_or_else(|_| panic!("Queue is full"));
@@ -18,3 +18,14 @@ pub struct CmdPacket {
     pub header: PacketHeader,
     pub cmdserial: CmdSerial,
 }
+
+impl Cmd {
+    pub fn cmdcode(&self) -> u16 {
+        self.cmdcode
+    }
+
+    /// Command payload, `plen` bytes long
+    pub fn payload(&self) -> &[u8] {
+        &self.payload[..self.plen as usize]
+    }
+}
@@ -1,3 +1,4 @@
+use super::consts::{TL_BLEEVT_CC_OPCODE, TL_BLEEVT_CS_OPCODE, TL_CS_EVT_SIZE};
 use super::PacketHeader;
 use crate::ipcc::Ipcc;
 use core::mem::MaybeUninit;
@@ -9,6 +10,22 @@ pub struct CsEvt {
     cmdcode: u16,
 }
 
+impl CsEvt {
+    pub fn status(&self) -> u8 {
+        self.status
+    }
+
+    /// Number of commands the controller can accept
+    pub fn numcmd(&self) -> u8 {
+        self.numcmd
+    }
+
+    /// Opcode of the command this status is for
+    pub fn cmdcode(&self) -> u16 {
+        self.cmdcode
+    }
+}
+
 #[repr(C, packed)]
 pub struct CcEvt {
     numcmd: u8,
@@ -29,6 +46,22 @@ pub struct Evt {
     payload: [u8; 1],
 }
 
+impl Evt {
+    pub fn evtcode(&self) -> u8 {
+        self.evtcode
+    }
+
+    /// Event payload, `plen` bytes long
+    ///
+    /// Only valid when borrowed in place from the event buffer, see [`EvtBox::as_packet`]
+    pub fn payload(&self) -> &[u8] {
+        let ptr = core::ptr::addr_of!(self.payload).cast::<u8>();
+
+        // SAFETY: The payload is a flexible array member, CPU2 writes `plen` bytes into the buffer
+        unsafe { core::slice::from_raw_parts(ptr, self.plen as usize) }
+    }
+}
+
 #[repr(C, packed)]
 pub struct EvtSerial {
     kind: u8,
@@ -49,6 +82,33 @@ impl EvtPacket {
     pub fn evt(&self) -> &Evt {
         &self.evtserial.evt
     }
+
+    /// The command status event, `None` for any other event
+    ///
+    /// Only valid when borrowed in place from the event buffer, see [`EvtBox::as_packet`]
+    pub fn cs_evt(&self) -> Option<&CsEvt> {
+        let evt = self.evt();
+
+        if evt.evtcode() != TL_BLEEVT_CS_OPCODE || evt.payload().len() < TL_CS_EVT_SIZE {
+            return None;
+        }
+
+        // SAFETY: The payload holds a complete `CsEvt`, which is packed and thus unaligned
+        Some(unsafe { &*evt.payload().as_ptr().cast::<CsEvt>() })
+    }
+
+    /// Number of commands the controller can accept, as reported by a command status or
+    /// command complete event
+    pub fn numcmd(&self) -> Option<u8> {
+        if let Some(cs) = self.cs_evt() {
+            return Some(cs.numcmd());
+        }
+
+        match (self.evt().evtcode(), self.evt().payload()) {
+            (TL_BLEEVT_CC_OPCODE, [numcmd, ..]) => Some(*numcmd),
+            _ => None,
+        }
+    }
 }
 
 pub struct EvtBox(*mut EvtPacket);
@@ -58,6 +118,18 @@ impl EvtBox {
         Self(packet)
     }
 
+    /// Borrows the event packet in place
+    ///
+    /// The buffer is released when the box is dropped
+    pub fn as_packet(&self) -> &EvtPacket {
+        // SAFETY: The buffer is owned by this box until it's dropped
+        unsafe { &*self.0 }
+    }
+
+    /// Copies the event packet out of the buffer
+    ///
+    /// The copy only holds the first payload byte, the rest of the payload is lost
+    #[deprecated(note = "the copy truncates the payload, use `as_packet` instead")]
     pub fn evt(&self) -> EvtPacket {
         let mut evt = MaybeUninit::uninit();
         unsafe {
@@ -0,0 +1,151 @@
+//! HCI transport to the BLE controller on CPU2
+//!
+//! With a BLE controller firmware (e.g. `stm32wb5x_BLE_HCILayer_fw.bin`) on CPU2, the BLE channel
+//! carries plain HCI packets. [`BleHci`] exposes it as such, so a BLE host stack running on CPU1
+//! can drive the controller.
+
+use super::{
+    ble::Ble,
+    consts::{TL_BLEEVT_CC_OPCODE, TL_BLEEVT_CS_OPCODE, TL_BLEEVT_PKT_TYPE, TL_BLEEVT_VS_OPCODE},
+    evt::{EvtBox, EvtPacket},
+    HeaplessEvtQueue,
+};
+use crate::ipcc::Ipcc;
+
+/// Maximum parameter length of an HCI command
+pub const HCI_CMD_MAX_PARAMS: usize = 255;
+/// Maximum data length of an HCI ACL data packet, limited by the ACL buffer shared with CPU2
+pub const HCI_ACL_MAX_DATA: usize = 251;
+
+/// HCI packet from the host to the controller, without the packet indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HciPacket<'a> {
+    /// Command, at most [`HCI_CMD_MAX_PARAMS`] bytes of parameters
+    Command { opcode: u16, params: &'a [u8] },
+    /// ACL data, at most [`HCI_ACL_MAX_DATA`] bytes
+    ///
+    /// `handle` holds the packet boundary and broadcast flags in its upper 4 bits
+    AclData { handle: u16, data: &'a [u8] },
+}
+
+/// HCI event from the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HciEvent<'a> {
+    /// HCI_Command_Complete
+    CommandComplete {
+        num_hci_command_packets: u8,
+        opcode: u16,
+        return_params: &'a [u8],
+    },
+    /// HCI_Command_Status
+    CommandStatus {
+        status: u8,
+        num_hci_command_packets: u8,
+        opcode: u16,
+    },
+    /// Vendor specific event, i.e. an ACI event of the ST stack
+    VendorSpecific { subevent: u16, params: &'a [u8] },
+    /// Any other event, left to the host stack to decode
+    Other { code: u8, params: &'a [u8] },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The packet isn't an HCI event, holds the packet type
+    NotAnEvent(u8),
+    /// The event parameters are shorter than required by the event code
+    Truncated,
+}
+
+impl<'a> HciEvent<'a> {
+    /// Parses an event received on the BLE channel, see [`BleHci::read_event`]
+    pub fn parse(packet: &'a EvtPacket) -> Result<Self, ParseError> {
+        if packet.kind() != TL_BLEEVT_PKT_TYPE {
+            return Err(ParseError::NotAnEvent(packet.kind()));
+        }
+
+        Self::decode(packet.evt().evtcode(), packet.evt().payload())
+    }
+
+    fn decode(code: u8, params: &'a [u8]) -> Result<Self, ParseError> {
+        let evt = match (code, params) {
+            (TL_BLEEVT_CC_OPCODE, [num, lo, hi, return_params @ ..]) => Self::CommandComplete {
+                num_hci_command_packets: *num,
+                opcode: u16::from_le_bytes([*lo, *hi]),
+                return_params,
+            },
+            (TL_BLEEVT_CS_OPCODE, [status, num, lo, hi, ..]) => Self::CommandStatus {
+                status: *status,
+                num_hci_command_packets: *num,
+                opcode: u16::from_le_bytes([*lo, *hi]),
+            },
+            (TL_BLEEVT_VS_OPCODE, [lo, hi, params @ ..]) => Self::VendorSpecific {
+                subevent: u16::from_le_bytes([*lo, *hi]),
+                params,
+            },
+            (TL_BLEEVT_CC_OPCODE | TL_BLEEVT_CS_OPCODE | TL_BLEEVT_VS_OPCODE, _) => {
+                return Err(ParseError::Truncated)
+            }
+            (code, params) => Self::Other { code, params },
+        };
+
+        Ok(evt)
+    }
+}
+
+/// BLE channel as an HCI transport, see the [module documentation](self)
+///
+/// Events are moved into the queue by [`TlMbox::ble_evt_handler`](super::TlMbox::ble_evt_handler).
+pub struct BleHci<'a> {
+    ble: &'a mut Ble,
+}
+
+impl<'a> BleHci<'a> {
+    pub(super) fn new(ble: &'a mut Ble) -> Self {
+        Self { ble }
+    }
+
+    /// The controller can accept another command, see [`Ble::can_send_cmd`]
+    pub fn can_send_cmd(&self) -> bool {
+        self.ble.can_send_cmd()
+    }
+
+    /// Sends an HCI packet to the controller
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters or the data exceed [`HCI_CMD_MAX_PARAMS`] / [`HCI_ACL_MAX_DATA`]
+    pub fn write_hci(&mut self, ipcc: &mut Ipcc, packet: HciPacket<'_>) {
+        // The first byte is the packet indicator, it's filled in by `Ble`
+        match packet {
+            HciPacket::Command { opcode, params } => {
+                assert!(params.len() <= HCI_CMD_MAX_PARAMS);
+
+                let mut buf = [0; 4 + HCI_CMD_MAX_PARAMS];
+                buf[1..3].copy_from_slice(&opcode.to_le_bytes());
+                buf[3] = params.len() as u8;
+                buf[4..4 + params.len()].copy_from_slice(params);
+
+                self.ble.send_cmd(ipcc, &buf[..4 + params.len()]);
+            }
+            HciPacket::AclData { handle, data } => {
+                assert!(data.len() <= HCI_ACL_MAX_DATA);
+
+                let mut buf = [0; 5 + HCI_ACL_MAX_DATA];
+                buf[1..3].copy_from_slice(&handle.to_le_bytes());
+                buf[3..5].copy_from_slice(&(data.len() as u16).to_le_bytes());
+                buf[5..5 + data.len()].copy_from_slice(data);
+
+                self.ble.send_acl_data(ipcc, &buf[..5 + data.len()]);
+            }
+        }
+    }
+
+    /// Takes the next received event from `queue`, `None` if it's empty
+    ///
+    /// Parse it with [`HciEvent::parse`]. The event buffer is released to CPU2 when the
+    /// [`EvtBox`] is dropped, so drop it once the event is handled.
+    pub fn read_event(&mut self, queue: &mut HeaplessEvtQueue) -> Option<EvtBox> {
+        queue.dequeue()
+    }
+}
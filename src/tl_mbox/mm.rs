@@ -4,9 +4,10 @@ use core::marker::PhantomData;
 use super::{
     channel::c1::IPCC_MM_RELEASE_BUFFER_CHANNEL,
     evt::EvtPacket,
-    unsafe_linked_list::{init_head, insert_tail, is_empty, remove_head},
-    MemManagerTable, BLE_SPARE_EVT_BUF, EVT_POOL, FREE_BUF_QUEUE, LOCAL_FREE_BUF_QUEUE, POOL_SIZE,
-    SYS_SPARE_EVT_BUF, TL_MEM_MANAGER_TABLE,
+    unsafe_linked_list::{get_size, init_head, insert_tail, is_empty, remove_head},
+    MemManagerTable, BLE_SPARE_EVT_BUF, EVT_BUF_SIZE, EVT_POOL, FREE_BUF_QUEUE,
+    LOCAL_FREE_BUF_QUEUE, POOL_SIZE, SYS_SPARE_EVT_BUF, TL_MEM_MANAGER_TABLE, TRACES_EVT_POOL,
+    TRACES_POOL_SIZE,
 };
 use core::mem::MaybeUninit;
 
@@ -28,8 +29,8 @@ impl MemoryManager {
                 blepool: EVT_POOL.as_ptr().cast(),
                 blepoolsize: POOL_SIZE as u32,
                 pevt_free_buffer_queue: FREE_BUF_QUEUE.as_mut_ptr(),
-                traces_evt_pool: core::ptr::null(),
-                tracespoolsize: 0,
+                traces_evt_pool: TRACES_EVT_POOL.as_ptr().cast(),
+                tracespoolsize: TRACES_POOL_SIZE as u32,
             }));
         }
 
@@ -37,6 +38,21 @@ impl MemoryManager {
             _marker: PhantomData,
         }
     }
+
+    /// Number of event buffers in the BLE event pool
+    pub const fn pool_capacity() -> usize {
+        POOL_SIZE / EVT_BUF_SIZE
+    }
+
+    /// Number of event buffers released by CPU1 that CPU2 hasn't reclaimed yet
+    ///
+    /// Counts the buffers in the shared free buffer queue and the ones still waiting in the
+    /// local queue for the release channel.
+    pub fn free_buffer_count(&self) -> usize {
+        unsafe {
+            get_size(FREE_BUF_QUEUE.as_mut_ptr()) + get_size(LOCAL_FREE_BUF_QUEUE.as_mut_ptr())
+        }
+    }
 }
 
 pub fn evt_drop(evt: *mut EvtPacket, ipcc: &mut Ipcc) {
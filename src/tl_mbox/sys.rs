@@ -0,0 +1,142 @@
+use super::{
+    channel,
+    consts::TL_BLEEVT_VS_OPCODE,
+    evt::{EvtBox, EvtPacket},
+    unsafe_linked_list::{init_head, is_empty, remove_head},
+    HeaplessEvtQueue, SysTable, SYSTEM_EVT_QUEUE, SYS_CMD_BUFFER, TL_SYS_TABLE,
+};
+use crate::ipcc::Ipcc;
+use aligned::Aligned;
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use embedded_hal::delay::DelayNs;
+
+/// Sub event code of the system ready event (SHCI_SUB_EVT_CODE_READY)
+const SHCI_SUB_EVT_CODE_READY: u16 = 0x9200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// CPU2 didn't send the system ready event in time, usually because no firmware is flashed
+    Timeout,
+}
+
+/// Firmware running on CPU2, reported by the system ready event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunningMode {
+    /// The firmware upgrade service is running
+    Fus,
+    /// The wireless stack is running
+    WirelessStack,
+}
+
+impl RunningMode {
+    /// Decodes the system ready event, `None` for any other event
+    fn from_evt(evt: &EvtBox) -> Option<Self> {
+        let evt = evt.as_packet().evt();
+
+        if evt.evtcode() != TL_BLEEVT_VS_OPCODE {
+            return None;
+        }
+
+        match evt.payload() {
+            [lo, hi, rsp, ..] if u16::from_le_bytes([*lo, *hi]) == SHCI_SUB_EVT_CODE_READY => {
+                match rsp {
+                    0 => Some(Self::WirelessStack),
+                    1 => Some(Self::Fus),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// System channel, used for the SHCI commands and events
+pub struct Sys {
+    _marker: PhantomData<*const ()>,
+}
+
+impl Sys {
+    pub(super) fn new(ipcc: &mut Ipcc) -> Self {
+        unsafe {
+            init_head(SYSTEM_EVT_QUEUE.as_mut_ptr());
+
+            TL_SYS_TABLE = Aligned(MaybeUninit::new(SysTable {
+                pcmd_buffer: SYS_CMD_BUFFER.as_mut_ptr(),
+                sys_queue: SYSTEM_EVT_QUEUE.as_ptr(),
+            }));
+        }
+
+        ipcc.c1_set_rx_channel(channel::c2::IPCC_SYSTEM_EVENT_CHANNEL, true);
+
+        Self {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Polls for the system ready event CPU2 sends after booting
+    ///
+    /// Received system events are moved into `queue` first. Events preceding the ready event are
+    /// dropped, which hands their buffers back to CPU2, so `queue` should be dedicated to the
+    /// system channel.
+    pub fn wait_ready(
+        &mut self,
+        ipcc: &mut Ipcc,
+        queue: &mut HeaplessEvtQueue,
+    ) -> nb::Result<RunningMode, Infallible> {
+        if ipcc.is_rx_pending(channel::c2::IPCC_SYSTEM_EVENT_CHANNEL) {
+            self.evt_handler(ipcc, queue);
+        }
+
+        while let Some(evt) = queue.dequeue() {
+            if let Some(mode) = RunningMode::from_evt(&evt) {
+                return Ok(mode);
+            }
+        }
+
+        Err(nb::Error::WouldBlock)
+    }
+
+    /// Like [`wait_ready`](Self::wait_ready), but blocks for at most `timeout_ms` milliseconds
+    ///
+    /// Without a firmware on CPU2, the ready event never arrives and blocking on
+    /// [`wait_ready`](Self::wait_ready) hangs forever. This fails with [`Error::Timeout`] instead.
+    pub fn wait_ready_timeout(
+        &mut self,
+        ipcc: &mut Ipcc,
+        queue: &mut HeaplessEvtQueue,
+        delay: &mut impl DelayNs,
+        timeout_ms: u32,
+    ) -> Result<RunningMode, Error> {
+        for _ in 0..=timeout_ms {
+            match self.wait_ready(ipcc, queue) {
+                Ok(mode) => return Ok(mode),
+                Err(nb::Error::WouldBlock) => delay.delay_ms(1),
+            }
+        }
+
+        Err(Error::Timeout)
+    }
+
+    /// Moves all received system events into `queue`
+    pub(super) fn evt_handler(&mut self, ipcc: &mut Ipcc, queue: &mut HeaplessEvtQueue) {
+        unsafe {
+            let mut node_ptr = core::ptr::null_mut();
+            let node_ptr_ptr: *mut _ = &mut node_ptr;
+
+            while !is_empty(SYSTEM_EVT_QUEUE.as_mut_ptr()) {
+                remove_head(SYSTEM_EVT_QUEUE.as_mut_ptr(), node_ptr_ptr);
+
+                let event: *mut EvtPacket = node_ptr.cast();
+                let event = EvtBox::new(event);
+
+                queue
+                    .enqueue(event)
+                    .unwrap_or_else(|_| panic!("Queue is full"));
+            }
+        }
+
+        ipcc.c1_clear_flag_channel(channel::c2::IPCC_SYSTEM_EVENT_CHANNEL);
+    }
+}
@@ -0,0 +1,93 @@
+use super::{
+    channel,
+    cmd::CmdPacket,
+    consts::{TL_OTACK_PKT_TYPE, TL_OTCMD_PKT_TYPE},
+    ThreadTable, THREAD_CLI_CMD_BUFFER, THREAD_CLI_NOT_BUFFER, THREAD_NOTIF_ACK_BUFFER,
+    THREAD_OT_CMD_RSP_BUFFER, TL_THREAD_TABLE,
+};
+use crate::ipcc::Ipcc;
+use aligned::Aligned;
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+/// Maximum size of a Thread notification
+pub const THREAD_NOTIF_SIZE: usize = 255;
+
+pub type ThreadNotifQueue = heapless::spsc::Queue<heapless::Vec<u8, THREAD_NOTIF_SIZE>, 8>;
+
+/// OpenThread command / response and notification channels
+pub struct Thread {
+    _marker: PhantomData<*const ()>,
+}
+
+impl Thread {
+    pub(super) fn new(ipcc: &mut Ipcc) -> Self {
+        unsafe {
+            TL_THREAD_TABLE = Aligned(MaybeUninit::new(ThreadTable {
+                notack_buffer: THREAD_NOTIF_ACK_BUFFER.as_ptr().cast(),
+                clicmdrsp_buffer: THREAD_CLI_CMD_BUFFER.as_ptr().cast(),
+                otcmdrsp_buffer: THREAD_OT_CMD_RSP_BUFFER.as_ptr().cast(),
+                clinot_buffer: THREAD_CLI_NOT_BUFFER.as_ptr().cast(),
+            }));
+        }
+
+        ipcc.c1_set_rx_channel(channel::c2::IPCC_THREAD_NOTIFICATION_ACK_CHANNEL, true);
+
+        Self {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sends an OpenThread command, `buf` holds the command code, length and payload
+    ///
+    /// The response is read with [`read_ot_rsp`](Self::read_ot_rsp).
+    pub fn send_ot_cmd(&mut self, ipcc: &mut Ipcc, buf: &[u8]) {
+        unsafe {
+            let cmd_packet = THREAD_OT_CMD_RSP_BUFFER.as_mut_ptr();
+            let p_cmd: *mut _ = &mut (*cmd_packet).cmdserial.cmd;
+
+            core::ptr::copy(buf.as_ptr(), p_cmd.cast(), buf.len());
+
+            (*cmd_packet).cmdserial.kind = TL_OTCMD_PKT_TYPE;
+        }
+
+        ipcc.c1_set_flag_channel(channel::c1::IPCC_THREAD_OT_CMD_RSP_CHANNEL);
+        ipcc.c1_set_tx_channel(channel::c1::IPCC_THREAD_OT_CMD_RSP_CHANNEL, true);
+    }
+
+    /// Polls for the response to the last OpenThread command
+    ///
+    /// CPU2 writes the response into the command buffer and clears the channel flag, the
+    /// returned payload is valid until the next command is sent.
+    pub fn read_ot_rsp(&mut self, ipcc: &mut Ipcc) -> nb::Result<&[u8], Infallible> {
+        if ipcc.c1_is_active_flag(channel::c1::IPCC_THREAD_OT_CMD_RSP_CHANNEL) {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        ipcc.c1_set_tx_channel(channel::c1::IPCC_THREAD_OT_CMD_RSP_CHANNEL, false);
+
+        // SAFETY: CPU2 is done with the buffer once the flag is cleared
+        let cmd_packet: &CmdPacket = unsafe { &*THREAD_OT_CMD_RSP_BUFFER.as_ptr() };
+
+        Ok(cmd_packet.cmdserial.cmd.payload())
+    }
+
+    /// Copies the pending notification into `queue` and acknowledges it
+    ///
+    /// CPU2 reuses the notification buffer once acknowledged.
+    pub(super) fn notif_handler(&mut self, ipcc: &mut Ipcc, queue: &mut ThreadNotifQueue) {
+        unsafe {
+            let notif = THREAD_NOTIF_ACK_BUFFER.as_mut_ptr();
+            let payload = heapless::Vec::from_slice((*notif).cmdserial.cmd.payload()).unwrap();
+
+            queue
+                .enqueue(payload)
+                .unwrap_or_else(|_| panic!("Queue is full"));
+
+            (*notif).cmdserial.kind = TL_OTACK_PKT_TYPE;
+        }
+
+        ipcc.c1_clear_flag_channel(channel::c2::IPCC_THREAD_NOTIFICATION_ACK_CHANNEL);
+    }
+}
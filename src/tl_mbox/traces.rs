@@ -0,0 +1,56 @@
+use super::{
+    channel,
+    evt::{EvtBox, EvtPacket},
+    unsafe_linked_list::{init_head, is_empty, remove_head},
+    HeaplessEvtQueue, TracesTable, TL_TRACES_TABLE, TRACES_EVT_QUEUE,
+};
+use crate::ipcc::Ipcc;
+use aligned::Aligned;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+/// Consumer of the CPU2 debug trace stream
+pub struct Traces {
+    _marker: PhantomData<*const ()>,
+}
+
+impl Traces {
+    pub(super) fn new(ipcc: &mut Ipcc) -> Self {
+        unsafe {
+            init_head(TRACES_EVT_QUEUE.as_mut_ptr());
+
+            TL_TRACES_TABLE = Aligned(MaybeUninit::new(TracesTable {
+                traces_queue: TRACES_EVT_QUEUE.as_ptr().cast(),
+            }));
+        }
+
+        ipcc.c1_set_rx_channel(channel::c2::IPCC_TRACES_CHANNEL, true);
+
+        Self {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Moves all received trace packets into `queue`
+    ///
+    /// The trace buffers are handed back to CPU2 once the [`EvtBox`]es are dropped.
+    pub(super) fn evt_handler(&mut self, ipcc: &mut Ipcc, queue: &mut HeaplessEvtQueue) {
+        unsafe {
+            let mut node_ptr = core::ptr::null_mut();
+            let node_ptr_ptr: *mut _ = &mut node_ptr;
+
+            while !is_empty(TRACES_EVT_QUEUE.as_mut_ptr()) {
+                remove_head(TRACES_EVT_QUEUE.as_mut_ptr(), node_ptr_ptr);
+
+                let event: *mut EvtPacket = node_ptr.cast();
+                let event = EvtBox::new(event);
+
+                queue
+                    .enqueue(event)
+                    .unwrap_or_else(|_| panic!("Queue is full"));
+            }
+        }
+
+        ipcc.c1_clear_flag_channel(channel::c2::IPCC_TRACES_CHANNEL);
+    }
+}